@@ -360,6 +360,29 @@ pub unsafe fn print_cortexm_state(writer: &mut dyn Write) {
         ));
     }
 
+    print_cortexm_fault_status(writer);
+}
+
+/// Return the address that caused the last memory management fault, if the
+/// fault status registers hold a valid one.
+pub unsafe fn mpu_fault_address() -> Option<usize> {
+    let cfsr = syscall::SCB_REGISTERS[1];
+    let mmfar = syscall::SCB_REGISTERS[3];
+
+    // MMARVALID
+    if (cfsr & 0x80) == 0x80 {
+        Some(mmfar as usize)
+    } else {
+        None
+    }
+}
+
+/// Print only the raw Cortex-M fault status registers, for when the full
+/// output of `print_cortexm_state()` is too much.
+pub unsafe fn print_cortexm_fault_status(writer: &mut dyn Write) {
+    let cfsr = syscall::SCB_REGISTERS[1];
+    let hfsr = syscall::SCB_REGISTERS[2];
+
     if cfsr == 0 && hfsr == 0 {
         let _ = writer.write_fmt(format_args!("No Cortex-M faults detected.\r\n"));
     } else {
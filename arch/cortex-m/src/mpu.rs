@@ -7,7 +7,7 @@
 
 use core::cell::Cell;
 use core::cmp;
-use core::fmt;
+use core::fmt::{self, Write};
 use core::num::NonZeroUsize;
 
 use kernel::platform::mpu;
@@ -16,6 +16,7 @@ use kernel::utilities::math;
 use kernel::utilities::registers::interfaces::{Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, FieldValue, ReadOnly, ReadWrite};
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 
 /// Smallest allowable MPU region across all CortexM cores
 /// Individual cores may have bigger min sizes, but never lower than 32
@@ -146,6 +147,9 @@ pub struct MPU<const NUM_REGIONS: usize, const MIN_REGION_SIZE: usize> {
     /// is currently configured for so that the MPU can skip updating when the
     /// kernel returns to the same app.
     hardware_is_configured_for: OptionalCell<NonZeroUsize>,
+    /// Bitmask of regions locked with `lock_region`. Locked regions are never
+    /// allocated or rewritten by `configure_mpu`.
+    locked_regions: Cell<u32>,
 }
 
 impl<const NUM_REGIONS: usize, const MIN_REGION_SIZE: usize> MPU<NUM_REGIONS, MIN_REGION_SIZE> {
@@ -154,6 +158,7 @@ impl<const NUM_REGIONS: usize, const MIN_REGION_SIZE: usize> MPU<NUM_REGIONS, MI
             registers: MPU_BASE_ADDRESS,
             config_count: Cell::new(NonZeroUsize::MIN),
             hardware_is_configured_for: OptionalCell::empty(),
+            locked_regions: Cell::new(0),
         }
     }
 
@@ -186,23 +191,28 @@ pub struct CortexMConfig<const NUM_REGIONS: usize> {
 /// needs.
 const APP_MEMORY_REGION_MAX_NUM: usize = 1;
 
+/// Name of the access permissions encoded in the `AP` field of a region.
+fn access_str(access_bits: u32) -> &'static str {
+    match access_bits {
+        0b000 => "NoAccess",
+        0b001 => "PrivilegedOnly",
+        0b010 => "UnprivilegedReadOnly",
+        0b011 => "ReadWrite",
+        0b100 => "Reserved",
+        0b101 => "PrivilegedOnlyReadOnly",
+        0b110 => "ReadOnly",
+        0b111 => "ReadOnlyAlias",
+        _ => "ERR",
+    }
+}
+
 impl<const NUM_REGIONS: usize> fmt::Display for CortexMConfig<NUM_REGIONS> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "\r\n Cortex-M MPU")?;
         for (i, region) in self.regions.iter().enumerate() {
             if let Some(location) = region.location() {
                 let access_bits = region.attributes().read(RegionAttributes::AP);
-                let access_str = match access_bits {
-                    0b000 => "NoAccess",
-                    0b001 => "PrivilegedOnly",
-                    0b010 => "UnprivilegedReadOnly",
-                    0b011 => "ReadWrite",
-                    0b100 => "Reserved",
-                    0b101 => "PrivilegedOnlyReadOnly",
-                    0b110 => "ReadOnly",
-                    0b111 => "ReadOnlyAlias",
-                    _ => "ERR",
-                };
+                let access_str = access_str(access_bits);
                 let start = location.0 as usize;
                 write!(
                     f,
@@ -241,23 +251,35 @@ impl<const NUM_REGIONS: usize> fmt::Display for CortexMConfig<NUM_REGIONS> {
 }
 
 impl<const NUM_REGIONS: usize> CortexMConfig<NUM_REGIONS> {
-    fn unused_region_number(&self) -> Option<usize> {
+    fn unused_region_number(&self, locked_regions: u32) -> Option<usize> {
         for (number, region) in self.regions.iter().enumerate() {
             if number <= APP_MEMORY_REGION_MAX_NUM {
                 continue;
             }
+            if locked_regions & (1 << number) != 0 {
+                continue;
+            }
             if let None = region.location() {
                 return Some(number);
             }
         }
         None
     }
+
+    /// Whether this configuration holds a region in one of the locked hardware
+    /// regions, e.g. because it was allocated before the region was locked.
+    fn uses_locked_region(&self, locked_regions: u32) -> bool {
+        self.regions.iter().enumerate().any(|(number, region)| {
+            locked_regions & (1 << number) != 0 && region.location().is_some()
+        })
+    }
 }
 
 /// Struct storing configuration for a Cortex-M MPU region.
 #[derive(Copy, Clone)]
 pub struct CortexMRegion {
     location: Option<(*const u8, usize)>,
+    permissions: Option<mpu::Permissions>,
     base_address: FieldValue<u32, RegionBaseAddress::Register>,
     attributes: FieldValue<u32, RegionAttributes::Register>,
 }
@@ -336,6 +358,7 @@ impl CortexMRegion {
 
         Some(CortexMRegion {
             location: Some((logical_start, logical_size)),
+            permissions: Some(permissions),
             base_address,
             attributes,
         })
@@ -344,6 +367,7 @@ impl CortexMRegion {
     fn empty(region_num: usize) -> CortexMRegion {
         CortexMRegion {
             location: None,
+            permissions: None,
             base_address: RegionBaseAddress::VALID::UseRBAR
                 + RegionBaseAddress::REGION.val(region_num as u32),
             attributes: RegionAttributes::ENABLE::CLEAR,
@@ -354,6 +378,34 @@ impl CortexMRegion {
         self.location
     }
 
+    /// The start and size of the memory processes can access through this
+    /// region: its logical location, narrowed to the enabled sub-regions. For
+    /// the application memory regions this ends at the app break, rounded up
+    /// to a sub-region.
+    fn accessible_range(&self) -> Option<(usize, usize)> {
+        let (start, size) = self.location?;
+        let start = start as usize;
+        let region_size = 1usize << (self.attributes.read(RegionAttributes::SIZE) + 1);
+        let enabled = !self.attributes.read(RegionAttributes::SRD) & 0xff;
+        if region_size < 256 || enabled == 0xff {
+            return Some((start, size));
+        }
+        if enabled == 0 {
+            return None;
+        }
+
+        let region_start = (self.base_address.read(RegionBaseAddress::ADDR) << 5) as usize;
+        let subregion_size = region_size / 8;
+        let first = enabled.trailing_zeros() as usize;
+        let last = (u32::BITS - 1 - enabled.leading_zeros()) as usize;
+        let accessible_start = cmp::max(start, region_start + first * subregion_size);
+        let accessible_end = cmp::min(start + size, region_start + (last + 1) * subregion_size);
+        Some((
+            accessible_start,
+            accessible_end.saturating_sub(accessible_start),
+        ))
+    }
+
     fn base_address(&self) -> FieldValue<u32, RegionBaseAddress::Register> {
         self.base_address
     }
@@ -402,6 +454,140 @@ impl<const NUM_REGIONS: usize, const MIN_REGION_SIZE: usize> mpu::MPU
         self.registers.mpu_type.read(Type::DREGION) as usize
     }
 
+    fn min_region_size(&self) -> usize {
+        MIN_REGION_SIZE
+    }
+
+    fn describe_fault(&self, fault_addr: usize, writer: &mut dyn Write) {
+        // Where regions overlap the one with the highest number takes
+        // priority, so check them from the highest down. Disabled sub-regions
+        // do not match, leaving the address to lower regions.
+        for region_num in (0..NUM_REGIONS).rev() {
+            self.registers
+                .rnr
+                .write(RegionNumber::REGION.val(region_num as u32));
+            let attributes = self.registers.rasr.extract();
+            if !attributes.is_set(RegionAttributes::ENABLE) {
+                continue;
+            }
+
+            let start = (self.registers.rbar.read(RegionBaseAddress::ADDR) << 5) as usize;
+            let size = 1usize << (attributes.read(RegionAttributes::SIZE) + 1);
+            if fault_addr < start || fault_addr - start >= size {
+                continue;
+            }
+            let subregion = (fault_addr - start) / (size / 8);
+            if size >= 256 && (attributes.read(RegionAttributes::SRD) >> subregion) & 1 == 1 {
+                continue;
+            }
+
+            let access_bits = attributes.read(RegionAttributes::AP);
+            let _ = writer.write_fmt(format_args!(
+                "Faulting address {:#010X} is in MPU region {} [{:#010X}:{:#010X}]: {} ({:#x})\r\n",
+                fault_addr,
+                region_num,
+                start,
+                start + size,
+                access_str(access_bits),
+                access_bits,
+            ));
+            return;
+        }
+
+        let _ = writer.write_fmt(format_args!(
+            "Faulting address {:#010X} is not in any MPU region\r\n",
+            fault_addr
+        ));
+    }
+
+    fn regions(&self, out: &mut [mpu::MpuRegionInfo]) -> usize {
+        let mut count = 0;
+        for region_num in 0..NUM_REGIONS {
+            self.registers
+                .rnr
+                .write(RegionNumber::REGION.val(region_num as u32));
+            let attributes = self.registers.rasr.extract();
+            if !attributes.is_set(RegionAttributes::ENABLE) {
+                continue;
+            }
+
+            let start = (self.registers.rbar.read(RegionBaseAddress::ADDR) << 5) as usize;
+            let size = 1usize << (attributes.read(RegionAttributes::SIZE) + 1);
+            let execute = !attributes.is_set(RegionAttributes::XN);
+            let permissions = match attributes.read(RegionAttributes::AP) {
+                0b011 if execute => Some(mpu::Permissions::ReadWriteExecute),
+                0b011 => Some(mpu::Permissions::ReadWriteOnly),
+                0b010 | 0b110 | 0b111 if execute => Some(mpu::Permissions::ReadExecuteOnly),
+                0b010 | 0b110 | 0b111 => Some(mpu::Permissions::ReadOnly),
+                _ => None,
+            };
+
+            // Regions of at least 256 bytes are split into eight sub-regions
+            // that can be disabled individually. Report each run of enabled
+            // sub-regions as its own range.
+            let (subregions, disabled) = if size >= 256 {
+                (8, attributes.read(RegionAttributes::SRD))
+            } else {
+                (1, 0)
+            };
+            let subregion_size = size / subregions;
+            let mut subregion = 0;
+            while subregion < subregions {
+                if (disabled >> subregion) & 1 == 1 {
+                    subregion += 1;
+                    continue;
+                }
+                let first = subregion;
+                while subregion < subregions && (disabled >> subregion) & 1 == 0 {
+                    subregion += 1;
+                }
+
+                let Some(slot) = out.get_mut(count) else {
+                    return count;
+                };
+                *slot = mpu::MpuRegionInfo {
+                    start: start + first * subregion_size,
+                    size: (subregion - first) * subregion_size,
+                    permissions,
+                };
+                count += 1;
+            }
+        }
+        count
+    }
+
+    fn config_regions(&self, config: &Self::MpuConfig, out: &mut [mpu::MpuRegionInfo]) -> usize {
+        // Report what the process can access, not the whole power-of-two
+        // regions, so the sizes can be used to right-size memory requests.
+        let allocated = config.regions.iter().filter_map(|region| {
+            region
+                .accessible_range()
+                .map(|(start, size)| mpu::MpuRegionInfo {
+                    start,
+                    size,
+                    permissions: region.permissions,
+                })
+        });
+        let mut count = 0;
+        for (slot, info) in out.iter_mut().zip(allocated) {
+            *slot = info;
+            count += 1;
+        }
+        count
+    }
+
+    fn lock_region(&self, region_id: usize) -> Result<(), ErrorCode> {
+        // The Cortex-M MPU has no hardware lock bit, so the lock is only
+        // honored by this driver. The application memory regions are
+        // rewritten for every process and cannot be locked.
+        if region_id >= NUM_REGIONS || region_id <= APP_MEMORY_REGION_MAX_NUM {
+            return Err(ErrorCode::INVAL);
+        }
+        self.locked_regions
+            .set(self.locked_regions.get() | (1 << region_id));
+        Ok(())
+    }
+
     fn new_config(&self) -> Option<Self::MpuConfig> {
         let id = self.config_count.get();
         self.config_count.set(id.checked_add(1)?);
@@ -442,7 +628,13 @@ impl<const NUM_REGIONS: usize, const MIN_REGION_SIZE: usize> mpu::MPU
             }
         }
 
-        let region_num = config.unused_region_number()?;
+        // Refuse to extend a configuration that conflicts with a locked region.
+        let locked_regions = self.locked_regions.get();
+        if config.uses_locked_region(locked_regions) {
+            return None;
+        }
+
+        let region_num = config.unused_region_number(locked_regions)?;
 
         // Logical region
         let mut start = unallocated_memory_start as usize;
@@ -788,8 +980,29 @@ impl<const NUM_REGIONS: usize, const MIN_REGION_SIZE: usize> mpu::MPU
         // If the hardware is already configured for this app and the app's MPU
         // configuration has not changed, then skip the hardware update.
         if !self.hardware_is_configured_for.contains(&config.id) || config.is_dirty.get() {
-            // Set MPU regions
-            for region in config.regions.iter() {
+            let locked_regions = self.locked_regions.get();
+
+            // A configuration that holds a region in a locked slot cannot be
+            // applied as-is. Rather than silently dropping that region, refuse
+            // the configuration and disable every unlocked region so the
+            // process has no access at all until it is reconfigured.
+            if config.uses_locked_region(locked_regions) {
+                for number in 0..NUM_REGIONS {
+                    if locked_regions & (1 << number) == 0 {
+                        let region = CortexMRegion::empty(number);
+                        self.registers.rbar.write(region.base_address());
+                        self.registers.rasr.write(region.attributes());
+                    }
+                }
+                self.hardware_is_configured_for.clear();
+                return;
+            }
+
+            // Set MPU regions, leaving locked regions as they are
+            for (number, region) in config.regions.iter().enumerate() {
+                if locked_regions & (1 << number) != 0 {
+                    continue;
+                }
                 self.registers.rbar.write(region.base_address());
                 self.registers.rasr.write(region.attributes());
             }
@@ -798,3 +1011,94 @@ impl<const NUM_REGIONS: usize, const MIN_REGION_SIZE: usize> mpu::MPU
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::platform::mpu::MPU as _;
+
+    #[test]
+    fn test_config_regions_app_memory() {
+        let mpu = unsafe { MPU::<8, 32>::new() };
+        let mut config = mpu.new_config().unwrap();
+
+        // A 4 KiB process memory block is covered by two 2 KiB regions with
+        // 256 byte sub-regions. For 0x600 bytes of app memory the allocator
+        // enables 7 of them.
+        let (start, size) = mpu
+            .allocate_app_memory_region(
+                0x2000_0000 as *const u8,
+                0x10000,
+                0x1000,
+                0x600,
+                0x400,
+                mpu::Permissions::ReadWriteOnly,
+                &mut config,
+            )
+            .unwrap();
+        assert_eq!((start as usize, size), (0x2000_0000, 0x1000));
+
+        let mut regions = [mpu::MpuRegionInfo::default(); 8];
+        assert_eq!(mpu.config_regions(&config, &mut regions), 1);
+        assert_eq!(regions[0].start, 0x2000_0000);
+        assert_eq!(regions[0].size, 0x700);
+        assert!(matches!(
+            regions[0].permissions,
+            Some(mpu::Permissions::ReadWriteOnly)
+        ));
+
+        // Growing the app break past the first region enables sub-regions of
+        // the second one.
+        mpu.update_app_memory_region(
+            0x2000_0900 as *const u8,
+            0x2000_0c00 as *const u8,
+            mpu::Permissions::ReadWriteOnly,
+            &mut config,
+        )
+        .unwrap();
+        assert_eq!(mpu.config_regions(&config, &mut regions), 2);
+        assert_eq!((regions[0].start, regions[0].size), (0x2000_0000, 0x800));
+        assert_eq!((regions[1].start, regions[1].size), (0x2000_0800, 0x100));
+    }
+
+    #[test]
+    fn test_allocate_region_refuses_locked_slot() {
+        let mpu = unsafe { MPU::<8, 32>::new() };
+        let mut config = mpu.new_config().unwrap();
+
+        // The first free slot after the app memory regions is region 2.
+        mpu.allocate_region(
+            0x0001_0000 as *const u8,
+            0x1000,
+            0x1000,
+            mpu::Permissions::ReadExecuteOnly,
+            &mut config,
+        )
+        .unwrap();
+        assert_eq!(mpu.lock_region(2), Ok(()));
+
+        // The config now conflicts with the lock and can no longer grow.
+        assert!(mpu
+            .allocate_region(
+                0x0002_0000 as *const u8,
+                0x1000,
+                0x1000,
+                mpu::Permissions::ReadOnly,
+                &mut config,
+            )
+            .is_none());
+
+        // A fresh config skips the locked slot.
+        let mut config = mpu.new_config().unwrap();
+        mpu.allocate_region(
+            0x0002_0000 as *const u8,
+            0x1000,
+            0x1000,
+            mpu::Permissions::ReadOnly,
+            &mut config,
+        )
+        .unwrap();
+        assert!(config.regions[2].location().is_none());
+        assert!(config.regions[3].location().is_some());
+    }
+}
@@ -179,7 +179,7 @@ pub unsafe fn next_pending_with_mask(mask: (u128, u128)) -> Option<u32> {
         .enumerate()
     {
         let interrupt_mask = if block < 4 { mask.1 } else { mask.0 };
-        let ispr_masked = ispr.get() & !((interrupt_mask >> (32 * block % 4)) as u32);
+        let ispr_masked = ispr.get() & !((interrupt_mask >> (32 * (block % 4))) as u32);
 
         // If there are any high bits there is a pending interrupt
         if ispr_masked != 0 {
@@ -191,6 +191,46 @@ pub unsafe fn next_pending_with_mask(mask: (u128, u128)) -> Option<u32> {
     None
 }
 
+/// Fill `buf` with the indices (0-240) of the pending interrupts, lowest first,
+/// and return how many were written. Stops once `buf` is full.
+pub unsafe fn pending(buf: &mut [u32]) -> usize {
+    let pending = NVIC
+        .ispr
+        .iter()
+        .take(number_of_nvic_registers())
+        .enumerate()
+        .flat_map(|(block, ispr)| {
+            let ispr = ispr.get();
+            (0..32)
+                .filter(move |bit| ispr & (1 << bit) != 0)
+                .map(move |bit| block as u32 * 32 + bit)
+        });
+
+    let mut count = 0;
+    for (slot, interrupt) in buf.iter_mut().zip(pending) {
+        *slot = interrupt;
+        count += 1;
+    }
+    count
+}
+
+/// Copy the pending (ISPR) and active (IABR) interrupt bits.
+///
+/// `pending` and `active` get one word per block of 32 interrupts. Words for
+/// blocks the NVIC does not implement, or beyond the end of either slice, are
+/// left untouched.
+pub unsafe fn pending_and_active(pending: &mut [u32], active: &mut [u32]) {
+    let blocks = NVIC
+        .ispr
+        .iter()
+        .zip(NVIC.iabr.iter())
+        .take(number_of_nvic_registers());
+    for ((ispr, iabr), (pending, active)) in blocks.zip(pending.iter_mut().zip(active.iter_mut())) {
+        *pending = ispr.get();
+        *active = iabr.get();
+    }
+}
+
 pub unsafe fn has_pending() -> bool {
     NVIC.ispr
         .iter()
@@ -212,7 +252,7 @@ pub unsafe fn has_pending_with_mask(mask: (u128, u128)) -> bool {
         .enumerate()
         .fold(0, |i, (block, ispr)| {
             let interrupt_mask = if block < 4 { mask.1 } else { mask.0 };
-            (ispr.get() & !((interrupt_mask >> (32 * block % 4)) as u32)) | i
+            (ispr.get() & !((interrupt_mask >> (32 * (block % 4))) as u32)) | i
         })
         != 0
 }
@@ -252,4 +292,23 @@ impl Nvic {
 
         NVIC.icpr[idx / 32].set(1 << (self.0 & 31));
     }
+
+    /// Set pending state, so that the interrupt is taken as if the peripheral
+    /// had raised it
+    pub fn set_pending(&self) {
+        let idx = self.0 as usize;
+
+        NVIC.ispr[idx / 32].set(1 << (self.0 & 31));
+    }
+
+    /// Set the priority of the interrupt, lower values being more urgent.
+    /// Cores only implement the most significant bits of the priority, the
+    /// others are ignored.
+    pub fn set_priority(&self, priority: u8) {
+        let idx = self.0 as usize;
+        let shift = (self.0 & 3) * 8;
+        let ipr = &NVIC.ipr[idx / 4];
+
+        ipr.set((ipr.get() & !(0xff << shift)) | ((priority as u32) << shift));
+    }
 }
@@ -14,6 +14,16 @@ pub fn nop() {
     }
 }
 
+#[cfg(any(doc, all(target_arch = "arm", target_os = "none")))]
+#[inline(always)]
+/// DSB instruction
+pub fn dsb() {
+    use core::arch::asm;
+    unsafe {
+        asm!("dsb", options(nostack, preserves_flags));
+    }
+}
+
 #[cfg(any(doc, all(target_arch = "arm", target_os = "none")))]
 #[inline(always)]
 /// WFI instruction
@@ -23,18 +33,40 @@ pub unsafe fn wfi() {
 }
 
 #[cfg(any(doc, all(target_arch = "arm", target_os = "none")))]
-pub unsafe fn atomic<F, R>(f: F) -> R
-where
-    F: FnOnce() -> R,
-{
+/// Disable interrupts and return the previous PRIMASK, to be passed to
+/// `restore_interrupts()` at the end of the critical section.
+pub unsafe fn disable_interrupts() -> usize {
     use core::arch::asm;
+    let primask: u32;
+    asm!("mrs {}, PRIMASK", out(reg) primask, options(nomem, nostack, preserves_flags));
+
     // Set PRIMASK
     asm!("cpsid i", options(nomem, nostack));
+    primask as usize
+}
 
-    let res = f();
-
+#[cfg(any(doc, all(target_arch = "arm", target_os = "none")))]
+/// Re-enable interrupts, but only if they were enabled when
+/// `disable_interrupts()` returned `primask`. Otherwise, this ends a nested
+/// critical section and the outer one will re-enable them.
+pub unsafe fn restore_interrupts(primask: usize) {
+    use core::arch::asm;
     // Unset PRIMASK
-    asm!("cpsie i", options(nomem, nostack));
+    if primask & 0x1 == 0 {
+        asm!("cpsie i", options(nomem, nostack));
+    }
+}
+
+#[cfg(any(doc, all(target_arch = "arm", target_os = "none")))]
+pub unsafe fn atomic<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    // Save the current PRIMASK so that nested calls do not re-enable
+    // interrupts when they return.
+    let primask = disable_interrupts();
+    let res = f();
+    restore_interrupts(primask);
     res
 }
 
@@ -45,12 +77,28 @@ pub fn nop() {
     unimplemented!()
 }
 
+#[cfg(not(any(doc, all(target_arch = "arm", target_os = "none"))))]
+/// DSB instruction (mock)
+pub fn dsb() {
+    unimplemented!()
+}
+
 #[cfg(not(any(doc, all(target_arch = "arm", target_os = "none"))))]
 /// WFI instruction (mock)
 pub unsafe fn wfi() {
     unimplemented!()
 }
 
+#[cfg(not(any(doc, all(target_arch = "arm", target_os = "none"))))]
+pub unsafe fn disable_interrupts() -> usize {
+    unimplemented!()
+}
+
+#[cfg(not(any(doc, all(target_arch = "arm", target_os = "none"))))]
+pub unsafe fn restore_interrupts(_primask: usize) {
+    unimplemented!()
+}
+
 #[cfg(not(any(doc, all(target_arch = "arm", target_os = "none"))))]
 pub unsafe fn atomic<F, R>(_f: F) -> R
 where
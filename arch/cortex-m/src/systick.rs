@@ -10,6 +10,7 @@ use kernel::utilities::registers::{register_bitfields, FieldValue, ReadOnly, Rea
 use kernel::utilities::StaticRef;
 
 use core::num::NonZeroU32;
+use kernel::platform::scheduler_timer::Accounting;
 
 /// The `SysTickFrequencyCapability` allows the holder to change the Cortex M
 /// SysTick `hertz` field.
@@ -66,6 +67,15 @@ register_bitfields![u32,
 pub struct SysTick {
     hertz: Cell<u32>,
     external_clock: bool,
+    /// Whether the current timeslice was found to have expired by
+    /// `remaining_us()`. Reading `syst_csr` clears `COUNTFLAG`, so this keeps
+    /// the expiration visible to `get_remaining_us()`.
+    expired: Cell<bool>,
+    /// Whether the timer is paused by `pause()`, and if so whether its
+    /// interrupt was enabled before.
+    paused: Cell<Option<bool>>,
+    /// The policy selected with `set_accounting()`.
+    accounting: Cell<Accounting>,
 }
 
 const BASE_ADDR: *const SystickRegisters = 0xE000E010 as *const SystickRegisters;
@@ -80,6 +90,9 @@ impl SysTick {
         SysTick {
             hertz: Cell::new(0),
             external_clock: false,
+            expired: Cell::new(false),
+            paused: Cell::new(None),
+            accounting: Cell::new(Accounting::ChargeAll),
         }
     }
 
@@ -167,6 +180,8 @@ impl kernel::platform::scheduler_timer::SchedulerTimer for SysTick {
             .syst_rvr
             .write(ReloadValue::RELOAD.val(reload as u32));
         SYSTICK_BASE.syst_cvr.set(0);
+        self.expired.set(false);
+        self.paused.set(None);
 
         // OK, arm it
         // We really just need to set the TICKINT bit here, but can't use modify() because
@@ -181,9 +196,17 @@ impl kernel::platform::scheduler_timer::SchedulerTimer for SysTick {
         SYSTICK_BASE.syst_csr.set(0);
         SYSTICK_BASE.syst_rvr.set(0);
         SYSTICK_BASE.syst_cvr.set(0);
+        self.expired.set(false);
+        self.paused.set(None);
     }
 
     fn arm(&self) {
+        // Keep the counter stopped while paused, `resume()` applies this.
+        if self.paused.get().is_some() {
+            self.paused.set(Some(true));
+            return;
+        }
+
         let clock_source: FieldValue<u32, self::ControlAndStatus::Register> = if self.external_clock
         {
             // CLKSOURCE 0 --> external clock
@@ -202,6 +225,12 @@ impl kernel::platform::scheduler_timer::SchedulerTimer for SysTick {
     }
 
     fn disarm(&self) {
+        // Keep the counter stopped while paused, `resume()` applies this.
+        if self.paused.get().is_some() {
+            self.paused.set(Some(false));
+            return;
+        }
+
         let clock_source: FieldValue<u32, self::ControlAndStatus::Register> = if self.external_clock
         {
             // CLKSOURCE 0 --> external clock
@@ -222,11 +251,87 @@ impl kernel::platform::scheduler_timer::SchedulerTimer for SysTick {
     fn get_remaining_us(&self) -> Option<NonZeroU32> {
         // use u64 in case of overflow when multiplying by 1,000,000
         let tics = SYSTICK_BASE.syst_cvr.read(CurrentValue::CURRENT) as u64;
-        if SYSTICK_BASE.syst_csr.is_set(ControlAndStatus::COUNTFLAG) {
+        if SYSTICK_BASE.syst_csr.is_set(ControlAndStatus::COUNTFLAG) || self.expired.get() {
             None
         } else {
             let hertz = self.hertz() as u64;
             NonZeroU32::new(((tics * 1_000_000) / hertz) as u32)
         }
     }
+
+    fn pause(&self) {
+        if self.paused.get().is_some() {
+            return;
+        }
+
+        // Reading the CSR clears COUNTFLAG, so remember an expiration.
+        let csr = SYSTICK_BASE.syst_csr.extract();
+        if csr.is_set(ControlAndStatus::COUNTFLAG) {
+            self.expired.set(true);
+        }
+        if !csr.is_set(ControlAndStatus::ENABLE) {
+            return;
+        }
+
+        // Clearing ENABLE stops the counter but keeps its current value.
+        let tickint = csr.is_set(ControlAndStatus::TICKINT);
+        SYSTICK_BASE.syst_csr.write(
+            ControlAndStatus::ENABLE::CLEAR
+                + ControlAndStatus::TICKINT.val(tickint as u32)
+                + ControlAndStatus::CLKSOURCE.val(csr.read(ControlAndStatus::CLKSOURCE)),
+        );
+        self.paused.set(Some(tickint));
+    }
+
+    fn resume(&self) {
+        if let Some(tickint) = self.paused.take() {
+            let clock_source: FieldValue<u32, self::ControlAndStatus::Register> =
+                if self.external_clock {
+                    // CLKSOURCE 0 --> external clock
+                    ControlAndStatus::CLKSOURCE::CLEAR
+                } else {
+                    // CLKSOURCE 1 --> internal clock
+                    ControlAndStatus::CLKSOURCE::SET
+                };
+            SYSTICK_BASE.syst_csr.write(
+                ControlAndStatus::ENABLE::SET
+                    + ControlAndStatus::TICKINT.val(tickint as u32)
+                    + clock_source,
+            );
+        }
+    }
+
+    fn set_accounting(&self, policy: Accounting) {
+        self.accounting.set(policy);
+    }
+
+    fn accounting(&self) -> Accounting {
+        self.accounting.get()
+    }
+
+    fn max_timeslice_us(&self) -> u32 {
+        // The reload value is 24 bits wide.
+        let max_tics = ReloadValue::RELOAD.mask as u64;
+        let hertz = self.hertz() as u64;
+        ((max_tics * 1_000_000) / hertz) as u32
+    }
+
+    fn remaining_us(&self) -> Option<u32> {
+        // `reset()` clears the reload value, so no timeslice is running.
+        if SYSTICK_BASE.syst_rvr.read(ReloadValue::RELOAD) == 0 {
+            return None;
+        }
+
+        // use u64 in case of overflow when multiplying by 1,000,000
+        let tics = SYSTICK_BASE.syst_cvr.read(CurrentValue::CURRENT) as u64;
+        if SYSTICK_BASE.syst_csr.is_set(ControlAndStatus::COUNTFLAG) {
+            self.expired.set(true);
+        }
+        if self.expired.get() {
+            None
+        } else {
+            let hertz = self.hertz() as u64;
+            Some(((tics * 1_000_000) / hertz) as u32)
+        }
+    }
 }
@@ -16,7 +16,9 @@ pub mod mpu {
 
 pub use cortexm::dwt;
 pub use cortexm::initialize_ram_jump_to_main;
+pub use cortexm::mpu_fault_address;
 pub use cortexm::nvic;
+pub use cortexm::print_cortexm_fault_status;
 pub use cortexm::scb;
 pub use cortexm::support;
 pub use cortexm::systick;
@@ -587,6 +587,11 @@ impl<const MAX_REGIONS: usize, P: TORUserPMP<MAX_REGIONS> + 'static> kernel::pla
         self.pmp.available_regions()
     }
 
+    fn min_region_size(&self) -> usize {
+        // TOR regions have a 4-byte granularity.
+        4
+    }
+
     fn new_config(&self) -> Option<Self::MpuConfig> {
         let id = self.config_count.get();
         self.config_count.set(id.checked_add(1)?);
@@ -24,27 +24,39 @@ pub unsafe fn wfi() {
     asm!("wfi", options(nomem, nostack));
 }
 
-pub unsafe fn atomic<F, R>(f: F) -> R
-where
-    F: FnOnce() -> R,
-{
+/// Disable machine mode interrupts and return the previous value of the
+/// [`mstatus::mie`] field, to be passed to `restore_interrupts()` at the end
+/// of the critical section.
+pub unsafe fn disable_interrupts() -> usize {
     // Read the mstatus MIE field and disable machine mode interrupts
     // atomically
     //
     // The result will be the original value of [`mstatus::mie`],
     // shifted to the proper position in [`mstatus`].
-    let original_mie: usize = CSR
-        .mstatus
+    CSR.mstatus
         .read_and_clear_bits(mstatus::mie.mask << mstatus::mie.shift)
-        & mstatus::mie.mask << mstatus::mie.shift;
+        & mstatus::mie.mask << mstatus::mie.shift
+}
+
+/// Re-enable machine mode interrupts if they were enabled when
+/// `disable_interrupts()` returned `original_mie`.
+pub unsafe fn restore_interrupts(original_mie: usize) {
+    // If [`mstatus::mie`] was set before, set it again. Otherwise,
+    // this function will be a nop.
+    CSR.mstatus.read_and_set_bits(original_mie);
+}
+
+pub unsafe fn atomic<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let original_mie = disable_interrupts();
 
     // Machine mode interrupts are disabled, execute the atomic
     // (uninterruptible) function
     let res = f();
 
-    // If [`mstatus::mie`] was set before, set it again. Otherwise,
-    // this function will be a nop.
-    CSR.mstatus.read_and_set_bits(original_mie);
+    restore_interrupts(original_mie);
 
     res
 }
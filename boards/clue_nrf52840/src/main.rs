@@ -195,6 +195,10 @@ pub struct Platform {
             >,
             capsules_core::virtualizers::virtual_pwm::PwmPinUser<'static, nrf52840::pwm::Pwm>,
         >,
+        capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<
+            'static,
+            nrf52840::rtc::Rtc<'static>,
+        >,
     >,
     adc: &'static capsules_core::adc::AdcVirtualized<'static>,
     temperature: &'static TemperatureDriver,
@@ -454,9 +458,14 @@ unsafe fn start() -> (
                 >,
                 capsules_core::virtualizers::virtual_pwm::PwmPinUser<'static, nrf52840::pwm::Pwm>,
             >,
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<
+                'static,
+                nrf52840::rtc::Rtc,
+            >,
         >,
         capsules_extra::buzzer_driver::Buzzer::new(
             pwm_buzzer,
+            virtual_alarm_buzzer,
             capsules_extra::buzzer_driver::DEFAULT_MAX_BUZZ_TIME_MS,
             board_kernel.create_grant(
                 capsules_extra::buzzer_driver::DRIVER_NUM,
@@ -9,10 +9,12 @@ use kernel::debug;
 use kernel::debug::IoWrite;
 use kernel::hil::led;
 use kernel::hil::uart::{self, Configure};
+use kernel::platform::watchdog::WatchDog;
 
 use crate::CHIP;
 use crate::PROCESSES;
 use crate::PROCESS_PRINTER;
+use crate::WATCHDOG;
 
 struct Writer {
     initialized: bool,
@@ -72,6 +74,9 @@ pub unsafe fn panic_fmt(pi: &PanicInfo) -> ! {
     let red_pin = sam4l::gpio::GPIOPin::new(sam4l::gpio::Pin::PA13);
     let led_red = &mut led::LedLow::new(&red_pin);
     let writer = &mut *addr_of_mut!(WRITER);
+    if let Some(ms) = (*addr_of!(WATCHDOG)).and_then(|wdt| wdt.time_since_last_pet()) {
+        let _ = writeln!(writer, "Watchdog last tickled {} ms ago", ms);
+    }
     debug::panic(
         &mut [led_red],
         writer,
@@ -45,6 +45,7 @@ static mut PROCESSES: [Option<&'static dyn kernel::process::Process>; NUM_PROCS]
 static mut CHIP: Option<&'static sam4l::chip::Sam4l<Sam4lDefaultPeripherals>> = None;
 static mut PROCESS_PRINTER: Option<&'static capsules_system::process_printer::ProcessPrinterText> =
     None;
+static mut WATCHDOG: Option<&'static sam4l::wdt::Wdt> = None;
 
 /// Dummy buffer that causes the linker to reserve enough space for the stack.
 #[no_mangle]
@@ -96,6 +97,7 @@ struct Hail {
     dac: &'static capsules_extra::dac::Dac<'static>,
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm4::systick::SysTick,
+    watchdog: &'static sam4l::wdt::Wdt,
 }
 
 /// Mapping of integer syscalls to objects that implement syscalls.
@@ -137,7 +139,7 @@ impl KernelResources<sam4l::chip::Sam4l<Sam4lDefaultPeripherals>> for Hail {
     type ProcessFault = ();
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
-    type WatchDog = ();
+    type WatchDog = sam4l::wdt::Wdt;
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -156,7 +158,7 @@ impl KernelResources<sam4l::chip::Sam4l<Sam4lDefaultPeripherals>> for Hail {
         &self.systick
     }
     fn watchdog(&self) -> &Self::WatchDog {
-        &()
+        self.watchdog
     }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
@@ -255,6 +257,7 @@ unsafe fn start() -> (
         sam4l::chip::Sam4l<Sam4lDefaultPeripherals>,
         sam4l::chip::Sam4l::new(pm, peripherals)
     );
+    chip.set_flash_controller(&peripherals.flash_controller);
     CHIP = Some(chip);
 
     // Create capabilities that the board needs to call certain protected kernel
@@ -291,6 +294,15 @@ unsafe fn start() -> (
         .finalize(components::alarm_mux_component_static!(sam4l::ast::Ast));
     peripherals.ast.configure(mux_alarm);
 
+    // # WATCHDOG
+    // The kernel loop runs the SAM4L watchdog, which resets the board if the
+    // loop does not tickle it within 100ms. It is stopped while the chip
+    // sleeps. Tickles are timestamped with the AST so that the panic handler
+    // can report how long the loop went without tickling it.
+    let watchdog = static_init!(sam4l::wdt::Wdt, sam4l::wdt::Wdt::new());
+    watchdog.set_time_source(&peripherals.ast);
+    WATCHDOG = Some(watchdog);
+
     // Setup the console and the process inspection console.
     let console = components::console::ConsoleComponent::new(
         board_kernel,
@@ -515,6 +527,7 @@ unsafe fn start() -> (
         dac,
         scheduler,
         systick: cortexm4::systick::SysTick::new(),
+        watchdog,
     };
 
     // Setup the UART bus for nRF51 serialization..
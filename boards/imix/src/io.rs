@@ -8,10 +8,12 @@ use kernel::debug;
 use kernel::debug::IoWrite;
 use kernel::hil::led;
 use kernel::hil::uart::{self, Configure};
+use kernel::platform::watchdog::WatchDog;
 
 use crate::CHIP;
 use crate::PROCESSES;
 use crate::PROCESS_PRINTER;
+use crate::WATCHDOG;
 
 struct Writer {
     initialized: bool,
@@ -65,6 +67,9 @@ pub unsafe fn panic_fmt(pi: &PanicInfo) -> ! {
     let led_pin = sam4l::gpio::GPIOPin::new(sam4l::gpio::Pin::PC22);
     let led = &mut led::LedLow::new(&led_pin);
     let writer = &mut *addr_of_mut!(WRITER);
+    if let Some(ms) = (*addr_of!(WATCHDOG)).and_then(|wdt| wdt.time_since_last_pet()) {
+        let _ = writeln!(writer, "Watchdog last tickled {} ms ago", ms);
+    }
     debug::panic(
         &mut [led],
         writer,
@@ -102,6 +102,7 @@ static mut PROCESSES: [Option<&'static dyn kernel::process::Process>; NUM_PROCS]
 static mut CHIP: Option<&'static sam4l::chip::Sam4l<Sam4lDefaultPeripherals>> = None;
 static mut PROCESS_PRINTER: Option<&'static capsules_system::process_printer::ProcessPrinterText> =
     None;
+static mut WATCHDOG: Option<&'static sam4l::wdt::Wdt> = None;
 
 /// Dummy buffer that causes the linker to reserve enough space for the stack.
 #[no_mangle]
@@ -171,6 +172,7 @@ struct Imix {
         &'static capsules_extra::nonvolatile_storage_driver::NonvolatileStorage<'static>,
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm4::systick::SysTick,
+    watchdog: &'static sam4l::wdt::Wdt,
 }
 
 impl SyscallDriverLookup for Imix {
@@ -203,15 +205,43 @@ impl SyscallDriverLookup for Imix {
             _ => f(None),
         }
     }
+
+    fn driver_nums(&self) -> &'static [usize] {
+        DRIVER_NUMS
+    }
 }
 
+/// The drivers provided by `with_driver` above.
+const DRIVER_NUMS: &[usize] = &[
+    capsules_core::console_ordered::DRIVER_NUM,
+    capsules_core::gpio::DRIVER_NUM,
+    capsules_core::alarm::DRIVER_NUM,
+    capsules_core::spi_controller::DRIVER_NUM,
+    capsules_core::adc::DRIVER_NUM,
+    capsules_core::led::DRIVER_NUM,
+    capsules_core::button::DRIVER_NUM,
+    capsules_extra::analog_comparator::DRIVER_NUM,
+    capsules_extra::ambient_light::DRIVER_NUM,
+    capsules_extra::temperature::DRIVER_NUM,
+    capsules_extra::humidity::DRIVER_NUM,
+    capsules_extra::ninedof::DRIVER_NUM,
+    capsules_extra::crc::DRIVER_NUM,
+    capsules_extra::usb::usb_user::DRIVER_NUM,
+    capsules_extra::net::udp::DRIVER_NUM,
+    capsules_extra::nrf51822_serialization::DRIVER_NUM,
+    capsules_extra::nonvolatile_storage_driver::DRIVER_NUM,
+    capsules_core::rng::DRIVER_NUM,
+    kernel::ipc::DRIVER_NUM,
+];
+kernel::platform::assert_unique_drivers!(DRIVER_NUMS);
+
 impl KernelResources<sam4l::chip::Sam4l<Sam4lDefaultPeripherals>> for Imix {
     type SyscallDriverLookup = Self;
     type SyscallFilter = ();
     type ProcessFault = ();
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
-    type WatchDog = ();
+    type WatchDog = sam4l::wdt::Wdt;
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -230,7 +260,7 @@ impl KernelResources<sam4l::chip::Sam4l<Sam4lDefaultPeripherals>> for Imix {
         &self.systick
     }
     fn watchdog(&self) -> &Self::WatchDog {
-        &()
+        self.watchdog
     }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
@@ -338,6 +368,12 @@ unsafe fn start() -> (
         sam4l::chip::Sam4l<Sam4lDefaultPeripherals>,
         sam4l::chip::Sam4l::new(pm, peripherals)
     );
+    chip.set_flash_controller(&peripherals.flash_controller);
+    let dma_dispatcher = static_init!(
+        sam4l::dma::DMA,
+        sam4l::dma::DMA::new(&peripherals.dma_channels)
+    );
+    chip.set_dma_dispatcher(dma_dispatcher);
     CHIP = Some(chip);
 
     // Create capabilities that the board needs to call certain protected kernel
@@ -373,6 +409,15 @@ unsafe fn start() -> (
         .finalize(components::alarm_mux_component_static!(sam4l::ast::Ast));
     peripherals.ast.configure(mux_alarm);
 
+    // # WATCHDOG
+    // The kernel loop runs the SAM4L watchdog, which resets the board if the
+    // loop does not tickle it within 100ms. It is stopped while the chip
+    // sleeps. Tickles are timestamped with the AST so that the panic handler
+    // can report how long the loop went without tickling it.
+    let watchdog = static_init!(sam4l::wdt::Wdt, sam4l::wdt::Wdt::new());
+    watchdog.set_time_source(&peripherals.ast);
+    WATCHDOG = Some(watchdog);
+
     let alarm =
         AlarmDriverComponent::new(board_kernel, capsules_core::alarm::DRIVER_NUM, mux_alarm)
             .finalize(components::alarm_component_static!(sam4l::ast::Ast));
@@ -764,6 +809,7 @@ unsafe fn start() -> (
         nonvolatile_storage,
         scheduler,
         systick: cortexm4::systick::SysTick::new(),
+        watchdog,
     };
 
     // Need to initialize the UART for the nRF51 serialization.
@@ -784,6 +830,7 @@ unsafe fn start() -> (
     //
     //test::virtual_uart_rx_test::run_virtual_uart_receive(uart_mux);
     //test::rng_test::run_entropy32(&peripherals.trng);
+    //test::dma_zero_length_test::run(&peripherals.dma_channels[15]);
     //test::virtual_aes_ccm_test::run(&peripherals.aes);
     //test::aes_test::run_aes128_ctr(&peripherals.aes);
     //test::aes_test::run_aes128_cbc(&peripherals.aes);
@@ -0,0 +1,61 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2022.
+
+//! Test that zero-length DMA transfers complete.
+//!
+//! To test, add the following line to the imix boot sequence:
+//! ```
+//!     test::dma_zero_length_test::run(&peripherals.dma_channels[15]);
+//! ```
+//! The channel must not be used by any other driver. You should see the
+//! following output:
+//! ```
+//!     DMA zero-length transfer: started
+//!     DMA zero-length transfer: done, 0 bytes, buffer returned
+//! ```
+
+use core::cell::Cell;
+use kernel::debug;
+use kernel::static_init;
+use sam4l::dma::{DMAChannel, DMAClient, DMAPeripheral, DMAWidth};
+
+struct DmaZeroLengthTest {
+    channel: &'static DMAChannel,
+    started: Cell<bool>,
+}
+
+impl DMAClient for DmaZeroLengthTest {
+    fn transfer_done(&self, _pid: DMAPeripheral) {
+        // The transfer must not complete from within `do_transfer`.
+        assert!(self.started.get());
+        match self.channel.abort_transfer() {
+            Some((_buf, bytes)) => debug!(
+                "DMA zero-length transfer: done, {} bytes, buffer returned",
+                bytes
+            ),
+            None => debug!("DMA zero-length transfer: done, but buffer lost"),
+        }
+        self.channel.disable();
+    }
+}
+
+pub unsafe fn run(channel: &'static DMAChannel) {
+    let buf = static_init!([u8; 4], [0; 4]);
+    let test = static_init!(
+        DmaZeroLengthTest,
+        DmaZeroLengthTest {
+            channel,
+            started: Cell::new(false),
+        }
+    );
+
+    channel.initialize(test, DMAWidth::Width8Bit);
+    channel.enable();
+    if let Err((err, _)) = channel.do_transfer(DMAPeripheral::USART0_TX, buf, 0) {
+        debug!("DMA zero-length transfer: could not start: {:?}", err);
+        return;
+    }
+    test.started.set(true);
+    debug!("DMA zero-length transfer: started");
+}
@@ -4,6 +4,7 @@
 
 pub(crate) mod aes_test;
 pub(crate) mod crc_test;
+pub(crate) mod dma_zero_length_test;
 pub(crate) mod i2c_dummy;
 pub(crate) mod icmp_lowpan_test;
 pub(crate) mod ipv6_lowpan_test;
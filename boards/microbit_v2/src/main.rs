@@ -144,6 +144,10 @@ pub struct MicroBit {
             >,
             capsules_core::virtualizers::virtual_pwm::PwmPinUser<'static, nrf52833::pwm::Pwm>,
         >,
+        capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<
+            'static,
+            nrf52833::rtc::Rtc<'static>,
+        >,
     >,
     pwm: &'static capsules_extra::pwm::Pwm<'static, 1>,
     app_flash: &'static capsules_extra::app_flash_driver::AppFlash<'static>,
@@ -401,9 +405,14 @@ unsafe fn start() -> (
                 >,
                 capsules_core::virtualizers::virtual_pwm::PwmPinUser<'static, nrf52833::pwm::Pwm>,
             >,
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<
+                'static,
+                nrf52833::rtc::Rtc,
+            >,
         >,
         capsules_extra::buzzer_driver::Buzzer::new(
             pwm_buzzer,
+            virtual_alarm_buzzer,
             capsules_extra::buzzer_driver::DEFAULT_MAX_BUZZ_TIME_MS,
             board_kernel.create_grant(
                 capsules_extra::buzzer_driver::DRIVER_NUM,
@@ -100,6 +100,10 @@ pub struct PicoExplorerBase {
                 rp2040::pwm::Pwm<'static>,
             >,
         >,
+        capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<
+            'static,
+            rp2040::timer::RPTimer<'static>,
+        >,
     >,
     button: &'static capsules_core::button::Button<'static, RPGpioPin<'static>>,
     screen: &'static capsules_extra::screen::Screen<'static>,
@@ -612,9 +616,14 @@ pub unsafe fn start() -> (
                 >,
                 capsules_core::virtualizers::virtual_pwm::PwmPinUser<'static, rp2040::pwm::Pwm>,
             >,
+            capsules_core::virtualizers::virtual_alarm::VirtualMuxAlarm<
+                'static,
+                rp2040::timer::RPTimer,
+            >,
         >,
         capsules_extra::buzzer_driver::Buzzer::new(
             pwm_buzzer,
+            virtual_alarm_buzzer,
             capsules_extra::buzzer_driver::DEFAULT_MAX_BUZZ_TIME_MS,
             board_kernel.create_grant(
                 capsules_extra::buzzer_driver::DRIVER_NUM,
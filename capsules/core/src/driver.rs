@@ -98,5 +98,7 @@ pub enum NUM {
     DateTime              = 0x90007,
     CycleCount            = 0x90008,
     Servo                 = 0x90009,
+    ProcessMemory         = 0x9000A,
+    SyscallDenial         = 0x9000B,
 }
 }
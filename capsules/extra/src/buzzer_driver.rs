@@ -4,14 +4,20 @@
 
 //! This provides virtualized userspace access to a buzzer.
 //!
-//! Each app can have one outstanding buzz request, and buzz requests will queue
-//! with each app getting exclusive access to the buzzer during its turn. Apps
-//! can specify the frequency and duration of the square wave buzz, but the
-//! duration is capped to prevent this from being annoying.
+//! Each app can have up to `MAX_QUEUED_BUZZES` outstanding buzz requests, and
+//! buzz requests will queue with each app getting exclusive access to the
+//! buzzer during its turn. Turns are handed out round-robin across apps, so an
+//! app with a long queue cannot starve the others. Apps can specify the
+//! frequency and duration of the square wave buzz, but the duration is capped
+//! to prevent this from being annoying.
 //!
 //! Apps can subscribe to an optional callback if they care about getting
 //! buzz done events.
 //!
+//! The capsule keeps a log of the last `EVENT_LOG_LEN` buzzes it started, with
+//! the time each started, which apps can read to diagnose alerts that did not
+//! sound when or as expected.
+//!
 //! Usage
 //! -----
 //!
@@ -51,9 +57,11 @@
 //!             capsules::virtual_alarm::VirtualMuxAlarm<'static, nrf52833::rtc::Rtc>,
 //!             capsules::virtual_pwm::PwmPinUser<'static, nrf52833::pwm::Pwm>,
 //!         >,
+//!         capsules::virtual_alarm::VirtualMuxAlarm<'static, nrf52833::rtc::Rtc>,
 //!     >,
 //!     capsules::buzzer_driver::Buzzer::new(
 //!         pwm_buzzer,
+//!         virtual_alarm_buzzer,
 //!         capsules::buzzer_driver::DEFAULT_MAX_BUZZ_TIME_MS,
 //!         board_kernel.create_grant(capsules::buzzer_driver::DRIVER_NUM, &memory_allocation_capability)
 //!     )
@@ -64,10 +72,12 @@
 //! virtual_alarm_buzzer.set_client(pwm_buzzer);
 //! ```
 
+use core::cell::Cell;
 use core::cmp;
 
 use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
 use kernel::hil;
+use kernel::hil::time::{ConvertTicks, Time};
 use kernel::syscall::{CommandReturn, SyscallDriver};
 use kernel::utilities::cells::OptionalCell;
 use kernel::{ErrorCode, ProcessId};
@@ -87,95 +97,209 @@ pub enum BuzzerCommand {
     },
 }
 
+/// Number of buzzes remembered in the event log.
+pub const EVENT_LOG_LEN: usize = 16;
+
+/// A buzz recorded in the event log.
+#[derive(Clone, Copy)]
+struct BuzzEvent {
+    /// Time the buzz started, in milliseconds of the capsule's time source.
+    /// This wraps around.
+    timestamp_ms: u32,
+    frequency_hz: usize,
+    duration_ms: usize,
+}
+
+/// Maximum number of buzz requests each app can have queued.
+pub const MAX_QUEUED_BUZZES: usize = 4;
+
 #[derive(Default)]
 pub struct App {
-    pending_command: Option<BuzzerCommand>, // What command to run when the buzzer is free.
+    /// Commands to run when the buzzer is free, oldest first.
+    pending_commands: [Option<BuzzerCommand>; MAX_QUEUED_BUZZES],
+}
+
+impl App {
+    fn push_command(&mut self, command: BuzzerCommand) -> Result<(), ErrorCode> {
+        self.pending_commands
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .map(|slot| *slot = Some(command))
+            .ok_or(ErrorCode::NOMEM)
+    }
+
+    fn pop_command(&mut self) -> Option<BuzzerCommand> {
+        let command = self.pending_commands[0].take();
+        self.pending_commands.rotate_left(1);
+        command
+    }
+
+    fn has_command(&self) -> bool {
+        self.pending_commands[0].is_some()
+    }
 }
 
-pub struct Buzzer<'a, B: hil::buzzer::Buzzer<'a>> {
+pub struct Buzzer<'a, B: hil::buzzer::Buzzer<'a>, T: Time> {
     /// The service capsule buzzer.
     buzzer: &'a B,
+    /// Time source used to timestamp the event log.
+    time: &'a T,
     /// Per-app state.
     apps: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
     /// Which app is currently using the buzzer.
     active_app: OptionalCell<ProcessId>,
+    /// Which app was given the buzzer most recently, to hand out turns
+    /// round-robin.
+    last_app: OptionalCell<ProcessId>,
     /// Max buzz time.
     max_duration_ms: usize,
+    /// The most recent buzzes, written round-robin.
+    event_log: [Cell<Option<BuzzEvent>>; EVENT_LOG_LEN],
+    /// Number of buzzes recorded in `event_log` since the capsule was
+    /// created. This wraps around.
+    events_logged: Cell<usize>,
 }
 
-impl<'a, B: hil::buzzer::Buzzer<'a>> Buzzer<'a, B> {
+impl<'a, B: hil::buzzer::Buzzer<'a>, T: Time> Buzzer<'a, B, T> {
     pub fn new(
         buzzer: &'a B,
+        time: &'a T,
         max_duration_ms: usize,
         grant: Grant<App, UpcallCount<1>, AllowRoCount<0>, AllowRwCount<0>>,
-    ) -> Buzzer<'a, B> {
+    ) -> Buzzer<'a, B, T> {
         Buzzer {
             buzzer,
+            time,
             apps: grant,
             active_app: OptionalCell::empty(),
+            last_app: OptionalCell::empty(),
             max_duration_ms,
+            event_log: [const { Cell::new(None) }; EVENT_LOG_LEN],
+            events_logged: Cell::new(0),
         }
     }
 
-    // Check so see if we are doing something. If not, go ahead and do this
-    // command. If so, this is queued and will be run when the pending
-    // command completes.
-    fn enqueue_command(
+    /// Start a buzz and record it in the event log if it started.
+    fn buzz(&self, frequency_hz: usize, duration_ms: usize) -> Result<(), ErrorCode> {
+        self.buzzer.buzz(frequency_hz, duration_ms)?;
+        let count = self.events_logged.get();
+        self.event_log[count % EVENT_LOG_LEN].set(Some(BuzzEvent {
+            timestamp_ms: self.time.ticks_to_ms(self.time.now()),
+            frequency_hz,
+            duration_ms,
+        }));
+        self.events_logged.set(count.wrapping_add(1));
+        Ok(())
+    }
+
+    /// The `n`th most recent buzz in the event log, 0 being the latest.
+    fn logged_event(&self, n: usize) -> Option<BuzzEvent> {
+        let count = self.events_logged.get();
+        if n >= EVENT_LOG_LEN {
+            return None;
+        }
+        self.event_log[count.wrapping_sub(n + 1) % EVENT_LOG_LEN].get()
+    }
+
+    /// Play a buzz for `processid` as soon as the buzzer is free. If no app is
+    /// using the buzzer it starts right away, otherwise the request is added
+    /// to the app's queue. Once the buzz has finished, the app's buzz done
+    /// upcall is scheduled.
+    ///
+    /// When the buzzer frees up, the next app after the one that just used
+    /// it, in process order, with a queued request gets its turn.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The buzz started or was queued.
+    /// - `NOMEM`: The app already has `MAX_QUEUED_BUZZES` requests queued.
+    pub fn buzz_queued(
         &self,
-        command: BuzzerCommand,
         processid: ProcessId,
+        frequency_hz: usize,
+        duration_ms: usize,
     ) -> Result<(), ErrorCode> {
+        let command = BuzzerCommand::Buzz {
+            frequency_hz,
+            duration_ms,
+        };
         if self.active_app.is_none() {
             // No app is currently using the buzzer, so we just use this app.
+            self.last_app.set(processid);
             self.active_app.set(processid);
-            match command {
-                BuzzerCommand::Buzz {
-                    frequency_hz,
-                    duration_ms,
-                } => self.buzzer.buzz(frequency_hz, duration_ms),
-            }
+            self.run_command(command)
         } else {
             // There is an active app, so queue this request (if possible).
             self.apps
-                .enter(processid, |app, _| {
-                    // Some app is using the storage, we must wait.
-                    if app.pending_command.is_some() {
-                        // No more room in the queue, nowhere to store this
-                        // request.
-                        Err(ErrorCode::NOMEM)
-                    } else {
-                        // We can store this, so lets do it.
-                        app.pending_command = Some(command);
-                        Ok(())
-                    }
-                })
+                .enter(processid, |app, _| app.push_command(command))
                 .unwrap_or_else(|err| err.into())
         }
     }
 
-    fn check_queue(&self) {
+    fn run_command(&self, command: BuzzerCommand) -> Result<(), ErrorCode> {
+        match command {
+            BuzzerCommand::Buzz {
+                frequency_hz,
+                duration_ms,
+            } => self.buzz(frequency_hz, duration_ms),
+        }
+    }
+
+    /// Returns the app whose turn it is next: the first app with a queued
+    /// request after the app that used the buzzer last, wrapping around.
+    fn next_app(&self) -> Option<ProcessId> {
+        let last = self.last_app.map_or(0, |processid| processid.id());
+        let mut first = None;
+        let mut next_after_last = None;
         for appiter in self.apps.iter() {
             let processid = appiter.processid();
-            let started_command = appiter.enter(|app, _| {
-                // If this app has a pending command let's use it.
-                app.pending_command.take().is_some_and(|command| {
-                    // Mark this driver as being in use.
-                    self.active_app.set(processid);
-                    // Actually make the buzz happen.
-                    match command {
-                        BuzzerCommand::Buzz {
-                            frequency_hz,
-                            duration_ms,
-                        } => self.buzzer.buzz(frequency_hz, duration_ms) == Ok(()),
-                    }
+            if !appiter.enter(|app, _| app.has_command()) {
+                continue;
+            }
+            if first.is_none_or(|p: ProcessId| processid.id() < p.id()) {
+                first = Some(processid);
+            }
+            if processid.id() > last
+                && next_after_last.is_none_or(|p: ProcessId| processid.id() < p.id())
+            {
+                next_after_last = Some(processid);
+            }
+        }
+        next_after_last.or(first)
+    }
+
+    fn check_queue(&self) {
+        while let Some(processid) = self.next_app() {
+            self.last_app.set(processid);
+            let started_command = self
+                .apps
+                .enter(processid, |app, _| {
+                    app.pop_command().is_some_and(|command| {
+                        // Mark this driver as being in use.
+                        self.active_app.set(processid);
+                        // Actually make the buzz happen.
+                        self.run_command(command) == Ok(())
+                    })
                 })
-            });
+                .unwrap_or(false);
             if started_command {
                 break;
             }
+            self.active_app.clear();
         }
     }
 
+    /// Whether the buzzer can play `frequency_hz`.
+    fn frequency_supported(&self, frequency_hz: usize) -> bool {
+        let (min, max) = self.buzzer.frequency_range();
+        (min..=max).contains(&frequency_hz)
+    }
+
+    /// Returns the app currently using the buzzer, if any.
+    pub fn current_owner(&self) -> Option<ProcessId> {
+        self.active_app.get()
+    }
+
     /// For buzzing immediatelly
     /// Checks whether an app is valid or not. The app is valid if
     /// there is no current active_app using the driver, or if the app corresponds
@@ -187,7 +311,7 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> Buzzer<'a, B> {
     }
 }
 
-impl<'a, B: hil::buzzer::Buzzer<'a>> hil::buzzer::BuzzerClient for Buzzer<'a, B> {
+impl<'a, B: hil::buzzer::Buzzer<'a>, T: Time> hil::buzzer::BuzzerClient for Buzzer<'a, B, T> {
     fn buzzer_done(&self, status: Result<(), ErrorCode>) {
         // Mark the active app as None and see if there is a callback.
         self.active_app.take().map(|processid| {
@@ -207,7 +331,7 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> hil::buzzer::BuzzerClient for Buzzer<'a, B>
 }
 
 /// Provide an interface for userland.
-impl<'a, B: hil::buzzer::Buzzer<'a>> SyscallDriver for Buzzer<'a, B> {
+impl<'a, B: hil::buzzer::Buzzer<'a>, T: Time> SyscallDriver for Buzzer<'a, B, T> {
     // Setup callbacks.
     //
     // ### `subscribe_num`
@@ -221,11 +345,21 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> SyscallDriver for Buzzer<'a, B> {
     /// - `0`: Return Ok(()) if this driver is included on the platform.
     /// - `1`: Buzz the buzzer when available. `data1` is used for the frequency in hertz, and
     ///   `data2` is the duration in ms. Note the duration is capped at 5000
-    ///   milliseconds.
+    ///   milliseconds. A duration of 0 buzzes until the buzzer is stopped.
+    ///   Returns NOMEM if the app already has `MAX_QUEUED_BUZZES` buzzes
+    ///   queued.
     /// - `2`: Buzz the buzzer immediatelly. `data1` is used for the frequency in hertz, and
     ///   `data2` is the duration in ms. Note the duration is capped at 5000
-    ///   milliseconds.
+    ///   milliseconds. A duration of 0 buzzes until the buzzer is stopped.
     /// - `3`: Stop the buzzer.
+    /// - `4`: Return the identifier of the app currently using the buzzer, or
+    ///   OFF if no app is using it.
+    /// - `5`: Return the lowest and highest frequency in hertz the buzzer can
+    ///   play. Commands `1` and `2` return INVAL for other frequencies.
+    /// - `6`: Return the `data1`th most recent buzz started, 0 being the
+    ///   latest, as its start time in milliseconds (wrapping around), its
+    ///   frequency in hertz and its duration in ms. Returns INVAL if fewer
+    ///   buzzes are in the log, which holds the last `EVENT_LOG_LEN`.
     fn command(
         &self,
         command_num: usize,
@@ -241,14 +375,11 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> SyscallDriver for Buzzer<'a, B> {
             1 => {
                 let frequency_hz = data1;
                 let duration_ms = cmp::min(data2, self.max_duration_ms);
-                self.enqueue_command(
-                    BuzzerCommand::Buzz {
-                        frequency_hz,
-                        duration_ms,
-                    },
-                    processid,
-                )
-                .into()
+                if !self.frequency_supported(frequency_hz) {
+                    return CommandReturn::failure(ErrorCode::INVAL);
+                }
+                self.buzz_queued(processid, frequency_hz, duration_ms)
+                    .into()
             }
 
             // Play a sound immediately.
@@ -256,11 +387,13 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> SyscallDriver for Buzzer<'a, B> {
                 if !self.is_valid_app(processid) {
                     // A different app is trying to use the buzzer, so we return RESERVE.
                     CommandReturn::failure(ErrorCode::RESERVE)
+                } else if !self.frequency_supported(data1) {
+                    CommandReturn::failure(ErrorCode::INVAL)
                 } else {
                     // If there is no active app or the same app is trying to use the buzzer,
                     // we set/replace the frequency and duration.
                     self.active_app.set(processid);
-                    self.buzzer.buzz(data1, data2).into()
+                    self.buzz(data1, data2).into()
                 }
             }
 
@@ -277,6 +410,34 @@ impl<'a, B: hil::buzzer::Buzzer<'a>> SyscallDriver for Buzzer<'a, B> {
                 }
             }
 
+            // Report which app is using the buzzer.
+            4 => self
+                .current_owner()
+                .map_or(CommandReturn::failure(ErrorCode::OFF), |owner| {
+                    CommandReturn::success_u32(owner.id() as u32)
+                }),
+
+            // Report the supported frequency range.
+            5 => {
+                let (min, max) = self.buzzer.frequency_range();
+                CommandReturn::success_u32_u32(
+                    cmp::min(min, u32::MAX as usize) as u32,
+                    cmp::min(max, u32::MAX as usize) as u32,
+                )
+            }
+
+            // Read the event log.
+            6 => {
+                self.logged_event(data1)
+                    .map_or(CommandReturn::failure(ErrorCode::INVAL), |event| {
+                        CommandReturn::success_u32_u32_u32(
+                            event.timestamp_ms,
+                            cmp::min(event.frequency_hz, u32::MAX as usize) as u32,
+                            cmp::min(event.duration_ms, u32::MAX as usize) as u32,
+                        )
+                    })
+            }
+
             _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
         }
     }
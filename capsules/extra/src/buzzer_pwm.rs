@@ -50,17 +50,43 @@
 //!
 //! ```
 
+use core::cell::Cell;
 use core::cmp;
 
 use kernel::hil;
-use kernel::hil::buzzer::BuzzerClient;
-use kernel::hil::time::Frequency;
+use kernel::hil::buzzer::{BuzzerClient, RtttlNotes};
+use kernel::hil::time::{ConvertTicks, Frequency, Ticks};
 use kernel::utilities::cells::OptionalCell;
 use kernel::ErrorCode;
 
 /// Standard max buzz time.
 pub const DEFAULT_MAX_BUZZ_TIME_MS: usize = 5000;
 
+/// Number of volume steps used to fade out a sound in `stop_with_rampdown`.
+const RAMPDOWN_STEPS: usize = 10;
+
+/// Length of each beat's beep in `start_metronome`. This is short enough to
+/// leave a gap between beats at the fastest tempo.
+const METRONOME_BEEP_MS: u32 = 50;
+
+/// What the buzzer is playing, which decides what happens when the alarm
+/// fires.
+#[derive(Clone, Copy)]
+enum BuzzerMode {
+    /// Nothing is playing, or `stop()` was called and the alarm will end the
+    /// sound and call the client.
+    Idle,
+    /// A single note, click or fade out is playing.
+    Oneshot,
+    /// A sequence of notes is playing.
+    Sequence(&'static [(usize, usize)]),
+    /// An RTTTL melody is playing, with the notes not yet started.
+    Rtttl(RtttlNotes<'static>),
+    /// The metronome is running, with the frequency of its beeps and the
+    /// length of each beat in alarm ticks.
+    Metronome(usize, u32),
+}
+
 pub struct PwmBuzzer<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> {
     /// The underlying PWM generator to make the buzzer buzz.
     pwm_pin: &'a P,
@@ -70,6 +96,29 @@ pub struct PwmBuzzer<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> {
     max_duration_ms: usize,
     /// The client currently using the service capsule.
     client: OptionalCell<&'a dyn BuzzerClient>,
+    /// What the buzzer is currently playing.
+    mode: Cell<BuzzerMode>,
+    /// Index in the sequence being played of the note currently playing.
+    sequence_index: Cell<usize>,
+    /// Volume as a percentage, scaling the PWM duty cycle.
+    volume: Cell<u8>,
+    /// Frequency and duty cycle of the sound currently playing.
+    playing: Cell<(usize, usize)>,
+    /// Volume steps left before a fade out started by `stop_with_rampdown`
+    /// ends, or 0 if the sound is not fading out.
+    rampdown_steps: Cell<usize>,
+    /// Length of each fade out step, in alarm ticks.
+    rampdown_interval: Cell<u32>,
+    /// Whether the metronome is in the beep part of a beat.
+    metronome_beeping: Cell<bool>,
+    /// Whether `set_muted` muted the buzzer.
+    muted: Cell<bool>,
+    /// Frequency and duty cycle the PWM would currently output if the buzzer
+    /// were not muted, or `None` if it would be stopped.
+    output: OptionalCell<(usize, usize)>,
+    /// Whether the sound playing is a click started by `click`, which ends
+    /// without calling the client.
+    clicking: Cell<bool>,
 }
 
 impl<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> PwmBuzzer<'a, A, P> {
@@ -79,7 +128,82 @@ impl<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> PwmBuzzer<'a, A, P> {
             alarm,
             client: OptionalCell::empty(),
             max_duration_ms,
+            mode: Cell::new(BuzzerMode::Idle),
+            sequence_index: Cell::new(0),
+            volume: Cell::new(100),
+            playing: Cell::new((0, 0)),
+            rampdown_steps: Cell::new(0),
+            rampdown_interval: Cell::new(0),
+            metronome_beeping: Cell::new(false),
+            muted: Cell::new(false),
+            output: OptionalCell::empty(),
+            clicking: Cell::new(false),
+        }
+    }
+
+    /// Start the PWM at `frequency_hz` at the current volume.
+    fn start_tone(&self, frequency_hz: usize) -> Result<(), ErrorCode> {
+        // Full volume is a 50% duty cycle square wave.
+        let duty_cycle =
+            self.pwm_pin.get_maximum_duty_cycle() / 2 * self.volume.get() as usize / 100;
+        self.rampdown_steps.set(0);
+        self.clicking.set(false);
+        self.start_output(frequency_hz, duty_cycle)?;
+        self.playing.set((frequency_hz, duty_cycle));
+        Ok(())
+    }
+
+    /// Start the PWM, unless the buzzer is muted, in which case only remember
+    /// the output so that `set_muted` can resume it.
+    fn start_output(&self, frequency_hz: usize, duty_cycle: usize) -> Result<(), ErrorCode> {
+        if !self.muted.get() {
+            self.pwm_pin.start(frequency_hz, duty_cycle)?;
+        }
+        self.output.set((frequency_hz, duty_cycle));
+        Ok(())
+    }
+
+    /// Stop the PWM, if it is running.
+    fn stop_output(&self) -> Result<(), ErrorCode> {
+        self.output.clear();
+        if self.muted.get() {
+            Ok(())
+        } else {
+            self.pwm_pin.stop()
+        }
+    }
+
+    /// Return to `Idle`, stop the pin output and call the client with
+    /// `result`, or with the error from stopping the output if `result` is
+    /// `Ok`.
+    fn finish(&self, result: Result<(), ErrorCode>) {
+        self.mode.set(BuzzerMode::Idle);
+        let stopped = self.stop_output();
+        self.client
+            .map(|buzz_client| buzz_client.buzzer_done(result.and(stopped)));
+    }
+
+    fn play_note(&self, frequency_hz: usize, duration_ms: usize) -> Result<(), ErrorCode> {
+        let duration_ms_cmp = cmp::min(duration_ms, self.max_duration_ms);
+        // A frequency of 0 is a rest, which also ends any fade out.
+        if frequency_hz == 0 {
+            self.rampdown_steps.set(0);
+            self.clicking.set(false);
+            self.stop_output()?;
+        } else {
+            self.start_tone(frequency_hz)?;
+        }
+
+        // A duration of 0 plays until `stop()`, so make sure the alarm of any
+        // note this replaces does not end it.
+        if duration_ms == 0 {
+            return self.alarm.disarm();
         }
+
+        // Set an alarm for the given duration.
+        let interval = self.alarm.ticks_from_ms(duration_ms_cmp as u32);
+        self.alarm.set_alarm(self.alarm.now(), interval);
+        Ok(())
     }
 }
 
@@ -91,33 +215,210 @@ impl<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> hil::buzzer::Buzzer<'a>
     }
 
     fn buzz(&self, frequency_hz: usize, duration_ms: usize) -> Result<(), ErrorCode> {
-        let duration_ms_cmp = cmp::min(duration_ms, self.max_duration_ms);
-        self.pwm_pin
-            .start(frequency_hz, self.pwm_pin.get_maximum_duty_cycle() / 2)?;
+        self.mode.set(BuzzerMode::Oneshot);
+        self.play_note(frequency_hz, duration_ms)
+            .inspect_err(|_| self.mode.set(BuzzerMode::Idle))
+    }
 
-        // Set an alarm for the given duration.
-        let interval = (duration_ms_cmp as u32) * <A::Frequency>::frequency() / 1000;
-        self.alarm
-            .set_alarm(self.alarm.now(), A::Ticks::from(interval));
+    fn buzz_sequence(&self, notes: &'static [(usize, usize)]) -> Result<(), ErrorCode> {
+        let (frequency_hz, duration_ms) = notes.first().ok_or(ErrorCode::INVAL)?;
+        self.mode.set(BuzzerMode::Sequence(notes));
+        self.sequence_index.set(0);
+        self.play_note(*frequency_hz, *duration_ms)
+            .inspect_err(|_| self.mode.set(BuzzerMode::Idle))
+    }
+
+    fn play_rtttl(&self, tune: &'static str) -> Result<(), ErrorCode> {
+        let mut notes = RtttlNotes::new(tune)?;
+        // Check every note before playing any.
+        while let Some(note) = notes.next_note() {
+            note?;
+        }
+
+        let mut notes = RtttlNotes::new(tune)?;
+        let (frequency_hz, duration_ms) = notes.next_note().ok_or(ErrorCode::INVAL)??;
+        self.mode.set(BuzzerMode::Rtttl(notes));
+        self.play_note(frequency_hz, duration_ms)
+            .inspect_err(|_| self.mode.set(BuzzerMode::Idle))
+    }
+
+    fn start_metronome(&self, bpm: usize, beep_freq_hz: usize) -> Result<(), ErrorCode> {
+        if !(hil::buzzer::METRONOME_MIN_BPM..=hil::buzzer::METRONOME_MAX_BPM).contains(&bpm) {
+            return Err(ErrorCode::INVAL);
+        }
+
+        self.mode.set(BuzzerMode::Idle);
+        self.start_tone(beep_freq_hz)?;
+        let beat = (<A::Frequency>::frequency() as u64 * 60 / bpm as u64) as u32;
+        self.mode.set(BuzzerMode::Metronome(beep_freq_hz, beat));
+        self.metronome_beeping.set(true);
+        self.alarm.set_alarm(
+            self.alarm.now(),
+            self.alarm.ticks_from_ms(METRONOME_BEEP_MS),
+        );
+        Ok(())
+    }
+
+    fn click(&self) -> Result<(), ErrorCode> {
+        if self.output.is_some() || self.alarm.is_armed() {
+            return Err(ErrorCode::BUSY);
+        }
+        self.start_tone(hil::buzzer::CLICK_FREQUENCY_HZ)?;
+        self.mode.set(BuzzerMode::Oneshot);
+        self.clicking.set(true);
+        self.alarm.set_alarm(
+            self.alarm.now(),
+            self.alarm.ticks_from_us(hil::buzzer::CLICK_DURATION_US),
+        );
+        Ok(())
+    }
+
+    fn set_volume(&self, percent: u8) -> Result<(), ErrorCode> {
+        if percent > 100 {
+            return Err(ErrorCode::INVAL);
+        }
+        self.volume.set(percent);
         Ok(())
     }
 
+    fn set_muted(&self, muted: bool) -> Result<(), ErrorCode> {
+        if self.muted.replace(muted) == muted {
+            return Ok(());
+        }
+        // Silence or resume the sound that is nominally playing, if any.
+        self.output.map_or(Ok(()), |(frequency_hz, duty_cycle)| {
+            if muted {
+                self.pwm_pin.stop()
+            } else {
+                self.pwm_pin.start(frequency_hz, duty_cycle)
+            }
+        })
+    }
+
+    fn frequency_range(&self) -> (usize, usize) {
+        // The maximum PWM frequency is derived from the clock driving the PWM.
+        (1, self.pwm_pin.get_maximum_frequency_hz())
+    }
+
     fn stop(&self) -> Result<(), ErrorCode> {
-        // Disarm the current alarm and instantly fire another.
+        // Cancel the rest of the sequence, the metronome or the fade out, if
+        // any. Whatever was playing, the alarm now only has to end the sound
+        // and call the client once.
+        self.mode.set(BuzzerMode::Idle);
+        self.rampdown_steps.set(0);
+        self.clicking.set(false);
+        // Disarm the current alarm and instantly fire another. Stopping again
+        // before it fires replaces it, so the client is still called once.
         self.alarm.disarm()?;
         // This method was used to reduce the size of the code.
         self.alarm.set_alarm(self.alarm.now(), A::Ticks::from(0));
         Ok(())
     }
+
+    fn stop_with_rampdown(&self, ms: usize) -> Result<(), ErrorCode> {
+        let ms = cmp::min(ms, self.max_duration_ms) as u32;
+        let interval = self.alarm.ticks_from_ms(ms).into_u32() / RAMPDOWN_STEPS as u32;
+        // The metronome beeps are too short to fade out, and there is nothing
+        // to fade out while idle or during a rest.
+        if interval == 0
+            || matches!(self.mode.get(), BuzzerMode::Metronome(..))
+            || self.output.is_none()
+        {
+            return self.stop();
+        }
+
+        self.mode.set(BuzzerMode::Oneshot);
+        self.clicking.set(false);
+        self.alarm.disarm()?;
+        self.rampdown_steps.set(RAMPDOWN_STEPS);
+        self.rampdown_interval.set(interval);
+        self.alarm
+            .set_alarm(self.alarm.now(), A::Ticks::from(interval));
+        Ok(())
+    }
 }
 
 impl<'a, A: hil::time::Alarm<'a>, P: hil::pwm::PwmPin> hil::time::AlarmClient
     for PwmBuzzer<'a, A, P>
 {
     fn alarm(&self) {
+        // A click ends without calling the client.
+        if self.clicking.replace(false) {
+            self.mode.set(BuzzerMode::Idle);
+            let _ = self.stop_output();
+            return;
+        }
+
+        // While the metronome is running, alternate between the beep and the
+        // silence of each beat.
+        if let BuzzerMode::Metronome(frequency_hz, beat) = self.mode.get() {
+            let beep = self.alarm.ticks_from_ms(METRONOME_BEEP_MS);
+            let result = if self.metronome_beeping.get() {
+                self.alarm.set_alarm(
+                    self.alarm.get_alarm(),
+                    A::Ticks::from(beat).wrapping_sub(beep),
+                );
+                self.stop_output()
+            } else {
+                self.alarm.set_alarm(self.alarm.get_alarm(), beep);
+                self.start_tone(frequency_hz)
+            };
+            match result {
+                Ok(()) => {
+                    self.metronome_beeping.set(!self.metronome_beeping.get());
+                    return;
+                }
+                Err(e) => {
+                    let _ = self.alarm.disarm();
+                    self.finish(Err(e));
+                    return;
+                }
+            }
+        }
+
+        // While fading out, lower the volume one step at a time until it
+        // reaches zero, then stop as usual.
+        let steps = self.rampdown_steps.get();
+        if steps > 1 {
+            let (frequency_hz, duty_cycle) = self.playing.get();
+            let result = self.start_output(frequency_hz, duty_cycle * (steps - 1) / RAMPDOWN_STEPS);
+            if result.is_ok() {
+                self.rampdown_steps.set(steps - 1);
+                self.alarm.set_alarm(
+                    self.alarm.now(),
+                    A::Ticks::from(self.rampdown_interval.get()),
+                );
+                return;
+            }
+        }
+        self.rampdown_steps.set(0);
+
+        // If we are playing a sequence, move on to the next note.
+        if let BuzzerMode::Sequence(notes) = self.mode.get() {
+            let index = self.sequence_index.get() + 1;
+            self.sequence_index.set(index);
+            if let Some((frequency_hz, duration_ms)) = notes.get(index) {
+                if let Err(e) = self.play_note(*frequency_hz, *duration_ms) {
+                    self.finish(Err(e));
+                }
+                return;
+            }
+        }
+
+        // Likewise for an RTTTL melody, whose notes were all checked when it
+        // started.
+        if let BuzzerMode::Rtttl(mut notes) = self.mode.get() {
+            if let Some(Ok((frequency_hz, duration_ms))) = notes.next_note() {
+                self.mode.set(BuzzerMode::Rtttl(notes));
+                if let Err(e) = self.play_note(frequency_hz, duration_ms) {
+                    self.finish(Err(e));
+                }
+                return;
+            }
+        }
+
         // Stop the pin output and signal that the buzzer has finished
         // playing.
-        self.client
-            .map(|buzz_client| buzz_client.buzzer_done(self.pwm_pin.stop()));
+        self.finish(Ok(()));
     }
 }
@@ -76,6 +76,7 @@ pub mod nrf51822_serialization;
 pub mod panic_button;
 pub mod pca9544a;
 pub mod pressure;
+pub mod process_memory;
 pub mod proximity;
 pub mod public_key_crypto;
 pub mod pwm;
@@ -100,6 +101,7 @@ pub mod sound_pressure;
 pub mod ssd1306;
 pub mod st77xx;
 pub mod symmetric_encryption;
+pub mod syscall_denial;
 pub mod temperature;
 pub mod temperature_rp2040;
 pub mod temperature_stm;
@@ -0,0 +1,117 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2022.
+
+//! Lets a process inspect the memory its MPU configuration gives it access
+//! to.
+//!
+//! This is intended for debugging: it reports the base address, size and
+//! permissions of each region, such as the process's RAM and flash, so app
+//! developers can right-size the memory they request with `elf2tab` instead
+//! of guessing.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! # use kernel::static_init;
+//!
+//! struct ProcessMgmtCap;
+//! unsafe impl capabilities::ProcessManagementCapability for ProcessMgmtCap {}
+//!
+//! let process_memory = static_init!(
+//!     capsules_extra::process_memory::ProcessMemory<ProcessMgmtCap>,
+//!     capsules_extra::process_memory::ProcessMemory::new(board_kernel, ProcessMgmtCap)
+//! );
+//! ```
+
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::ProcessMemory as usize;
+
+use kernel::capabilities::ProcessManagementCapability;
+use kernel::platform::mpu::{MpuRegionInfo, Permissions};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, Kernel, ProcessId};
+
+/// Maximum number of regions reported for a process.
+pub const MAX_REGIONS: usize = 8;
+
+pub struct ProcessMemory<C: ProcessManagementCapability> {
+    kernel: &'static Kernel,
+    capability: C,
+}
+
+impl<C: ProcessManagementCapability> ProcessMemory<C> {
+    pub fn new(kernel: &'static Kernel, capability: C) -> Self {
+        Self { kernel, capability }
+    }
+
+    /// Read the regions of `processid`'s MPU configuration into `regions` and
+    /// return how many there are.
+    fn regions(&self, processid: ProcessId, regions: &mut [MpuRegionInfo]) -> usize {
+        self.kernel.process_map_or_external(
+            0,
+            processid,
+            |process| process.mpu_regions(regions),
+            &self.capability,
+        )
+    }
+}
+
+/// Encode region permissions for userspace, see `command`.
+fn permissions_code(permissions: Option<Permissions>) -> u32 {
+    match permissions {
+        None => 0,
+        Some(Permissions::ReadWriteExecute) => 1,
+        Some(Permissions::ReadWriteOnly) => 2,
+        Some(Permissions::ReadExecuteOnly) => 3,
+        Some(Permissions::ReadOnly) => 4,
+        Some(Permissions::ExecuteOnly) => 5,
+    }
+}
+
+impl<C: ProcessManagementCapability> SyscallDriver for ProcessMemory<C> {
+    /// Inspect the calling process's MPU regions.
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Driver existence check.
+    /// - `1`: Return the number of regions, up to `MAX_REGIONS`.
+    /// - `2`: Return the start address, size in bytes and permissions of
+    ///   region `data1`. Permissions are encoded as 0 for no access, 1 for
+    ///   read-write-execute, 2 for read-write, 3 for read-execute, 4 for
+    ///   read-only and 5 for execute-only. Returns `INVAL` if there is no
+    ///   such region.
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        _: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        let mut regions = [MpuRegionInfo::default(); MAX_REGIONS];
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => CommandReturn::success_u32(self.regions(processid, &mut regions) as u32),
+
+            2 => {
+                let count = self.regions(processid, &mut regions);
+                match regions[..count].get(data1) {
+                    Some(region) => CommandReturn::success_u32_u32_u32(
+                        region.start as u32,
+                        region.size as u32,
+                        permissions_code(region.permissions),
+                    ),
+                    None => CommandReturn::failure(ErrorCode::INVAL),
+                }
+            }
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, _: ProcessId) -> Result<(), kernel::process::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+// Licensed under the Apache License, Version 2.0 or the MIT License.
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright Tock Contributors 2022.
+
+//! Lets a process ask why the board's system call filter denied one of its
+//! system calls.
+//!
+//! The `ErrorCode` a process gets back for a rejected system call cannot tell
+//! e.g. a rate limit apart from a missing permission. This capsule reports
+//! the reason from `SyscallFilter::syscall_denied_reason`, whose meaning is
+//! defined by the filter, such as `RATE_LIMIT_DENIED_REASON` for
+//! `RateLimitFilter`.
+//!
+//! The filter should not deny access to this driver, or processes cannot ask.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! # use kernel::static_init;
+//!
+//! struct ProcessMgmtCap;
+//! unsafe impl capabilities::ProcessManagementCapability for ProcessMgmtCap {}
+//!
+//! let syscall_denial = static_init!(
+//!     capsules_extra::syscall_denial::SyscallDenial<
+//!         RateLimitFilter<'static, Rtc, NUM_PROCS>,
+//!         ProcessMgmtCap,
+//!     >,
+//!     capsules_extra::syscall_denial::SyscallDenial::new(
+//!         board_kernel,
+//!         rate_limiter,
+//!         ProcessMgmtCap
+//!     )
+//! );
+//! ```
+
+use capsules_core::driver;
+pub const DRIVER_NUM: usize = driver::NUM::SyscallDenial as usize;
+
+use kernel::capabilities::ProcessManagementCapability;
+use kernel::platform::SyscallFilter;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, Kernel, ProcessId};
+
+pub struct SyscallDenial<F: SyscallFilter + 'static, C: ProcessManagementCapability> {
+    kernel: &'static Kernel,
+    filter: &'static F,
+    capability: C,
+}
+
+impl<F: SyscallFilter, C: ProcessManagementCapability> SyscallDenial<F, C> {
+    pub fn new(kernel: &'static Kernel, filter: &'static F, capability: C) -> Self {
+        Self {
+            kernel,
+            filter,
+            capability,
+        }
+    }
+}
+
+impl<F: SyscallFilter, C: ProcessManagementCapability> SyscallDriver for SyscallDenial<F, C> {
+    /// Query why system calls of the calling process were denied.
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Driver existence check.
+    /// - `1`: Return the filter-defined reason the most recent denied system
+    ///   call of the process was rejected. Returns `FAIL` if the filter has
+    ///   no reason to report.
+    fn command(
+        &self,
+        command_num: usize,
+        _: usize,
+        _: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => self
+                .kernel
+                .process_map_or_external(
+                    None,
+                    processid,
+                    |process| self.filter.syscall_denied_reason(process),
+                    &self.capability,
+                )
+                .map_or(CommandReturn::failure(ErrorCode::FAIL), |reason| {
+                    CommandReturn::success_u32(reason)
+                }),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, _: ProcessId) -> Result<(), kernel::process::Error> {
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@
 use core::fmt::Write;
 use kernel::debug;
 use kernel::hil::time::Freq32KHz;
+use kernel::platform::chip::CriticalToken;
 use kernel::platform::chip::InterruptService;
 use kernel::utilities::registers::interfaces::Readable;
 
@@ -189,6 +190,13 @@ impl<'a, I: InterruptService + 'a> kernel::platform::chip::Chip for ArtyExx<'a,
         rv32i::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            rv32i::support::disable_interrupts(),
+            rv32i::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, write: &mut dyn Write) {
         rv32i::print_riscv_state(write);
     }
@@ -15,6 +15,7 @@ use rv32i::pmp::{simple::SimplePMP, PMPUserMPU};
 
 use crate::plic::PLIC;
 use kernel::hil::time::Freq32KHz;
+use kernel::platform::chip::CriticalToken;
 use kernel::platform::chip::InterruptService;
 use sifive::plic::Plic;
 
@@ -174,6 +175,13 @@ impl<'a, I: InterruptService + 'a> kernel::platform::chip::Chip for E310x<'a, I>
         rv32i::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            rv32i::support::disable_interrupts(),
+            rv32i::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, writer: &mut dyn Write) {
         rv32i::print_riscv_state(writer);
     }
@@ -7,7 +7,7 @@
 use core::fmt::{Display, Write};
 use core::marker::PhantomData;
 use core::ptr::addr_of;
-use kernel::platform::chip::{Chip, InterruptService};
+use kernel::platform::chip::{Chip, CriticalToken, InterruptService};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use rv32i::csr::{mcause, mie::mie, mtvec::mtvec, CSR};
 use rv32i::pmp::{PMPUserMPU, TORUserPMP};
@@ -311,6 +311,13 @@ impl<
         rv32i::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            rv32i::support::disable_interrupts(),
+            rv32i::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, writer: &mut dyn Write) {
         let _ = writer.write_fmt(format_args!(
             "\r\n---| OpenTitan Earlgrey configuration for {} |---",
@@ -7,7 +7,7 @@
 use core::fmt::Write;
 use core::ptr::addr_of;
 
-use kernel::platform::chip::{Chip, InterruptService};
+use kernel::platform::chip::{Chip, CriticalToken, InterruptService};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::StaticRef;
 
@@ -153,6 +153,13 @@ impl<'a, I: InterruptService + 'a> Chip for Esp32C3<'a, I> {
         rv32i::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            rv32i::support::disable_interrupts(),
+            rv32i::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, writer: &mut dyn Write) {
         let mcval: csr::mcause::Trap = core::convert::From::from(csr::CSR.mcause.extract());
         let _ = writer.write_fmt(format_args!("\r\n---| RISC-V Machine State |---\r\n"));
@@ -7,7 +7,7 @@
 use core::fmt::Write;
 use cortexm7::{CortexM7, CortexMVariant};
 use kernel::debug;
-use kernel::platform::chip::{Chip, InterruptService};
+use kernel::platform::chip::{Chip, CriticalToken, InterruptService};
 
 use crate::nvic;
 
@@ -148,6 +148,13 @@ impl<I: InterruptService + 'static> Chip for Imxrt10xx<I> {
         cortexm7::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            cortexm7::support::disable_interrupts(),
+            cortexm7::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, write: &mut dyn Write) {
         CortexM7::print_cortexm_state(write);
     }
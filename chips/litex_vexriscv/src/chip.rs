@@ -7,6 +7,7 @@
 use core::fmt::Write;
 use core::ptr::addr_of;
 use kernel::debug;
+use kernel::platform::chip::CriticalToken;
 use kernel::platform::chip::InterruptService;
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable};
 use rv32i::csr::{mcause, mie::mie, CSR};
@@ -101,6 +102,13 @@ impl<I: 'static + InterruptService> kernel::platform::chip::Chip for LiteXVexRis
         rv32i::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            rv32i::support::disable_interrupts(),
+            rv32i::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, writer: &mut dyn Write) {
         let _ = writer.write_fmt(format_args!(
             "\r\n---| LiteX configuration for {} |---",
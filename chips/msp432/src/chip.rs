@@ -8,6 +8,7 @@ use kernel::platform::chip::Chip;
 
 use crate::nvic;
 use crate::wdt;
+use kernel::platform::chip::CriticalToken;
 use kernel::platform::chip::InterruptService;
 
 pub struct Msp432<'a, I: InterruptService + 'a> {
@@ -151,6 +152,13 @@ impl<'a, I: InterruptService + 'a> Chip for Msp432<'a, I> {
         cortexm4::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            cortexm4::support::disable_interrupts(),
+            cortexm4::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, write: &mut dyn Write) {
         CortexM4::print_cortexm_state(write);
     }
@@ -4,6 +4,7 @@
 
 use core::fmt::Write;
 use cortexm4f::{nvic, CortexM4F, CortexMVariant};
+use kernel::platform::chip::CriticalToken;
 use kernel::platform::chip::InterruptService;
 
 pub struct NRF52<'a, I: InterruptService + 'a> {
@@ -150,6 +151,13 @@ impl<'a, I: InterruptService + 'a> kernel::platform::chip::Chip for NRF52<'a, I>
         cortexm4f::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            cortexm4f::support::disable_interrupts(),
+            cortexm4f::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, write: &mut dyn Write) {
         CortexM4F::print_cortexm_state(write);
     }
@@ -3,6 +3,7 @@
 // Copyright OxidOS Automotive 2025 SRL.
 
 use kernel::platform::chip::Chip;
+use kernel::platform::chip::CriticalToken;
 use kernel::platform::chip::InterruptService;
 
 use crate::{cpuss, gpio, hsiom, peri, scb, srss, tcpwm};
@@ -45,6 +46,13 @@ impl<I: InterruptService> Chip for Psoc62xa<'_, I> {
         cortexm0p::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            cortexm0p::support::disable_interrupts(),
+            cortexm0p::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, writer: &mut dyn core::fmt::Write) {
         CortexM0P::print_cortexm_state(writer);
     }
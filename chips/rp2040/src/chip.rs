@@ -6,6 +6,7 @@
 
 use core::fmt::Write;
 use kernel::platform::chip::Chip;
+use kernel::platform::chip::CriticalToken;
 use kernel::platform::chip::InterruptService;
 
 use crate::adc;
@@ -114,6 +115,13 @@ impl<I: InterruptService> Chip for Rp2040<'_, I> {
         cortexm0p::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            cortexm0p::support::disable_interrupts(),
+            cortexm0p::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, writer: &mut dyn Write) {
         CortexM0P::print_cortexm_state(writer);
     }
@@ -770,7 +770,7 @@ impl<'a> hil::adc::Adc<'a> for Adc<'a> {
             // stop DMA transfer if going. This should safely return a None if
             // the DMA was not being used
             let dma_buffer = self.rx_dma.map_or(None, |rx_dma| {
-                let dma_buf = rx_dma.abort_transfer();
+                let dma_buf = rx_dma.abort_transfer().map(|(buf, _)| buf);
                 rx_dma.disable();
                 dma_buf
             });
@@ -909,12 +909,21 @@ impl<'a> hil::adc::AdcHighSpeed<'a> for Adc<'a> {
             let dma_buf = unsafe { slice::from_raw_parts_mut(dma_buf_ptr, buffer1.len() * 2) };
 
             // set up the DMA
-            self.rx_dma.map(move |dma| {
+            if let Some(dma) = self.rx_dma.get() {
                 self.dma_running.set(true);
                 dma.enable();
                 self.rx_length.set(dma_len);
-                dma.do_transfer(self.rx_dma_peripheral, dma_buf, dma_len);
-            });
+                if let Err((err, _)) = dma.do_transfer(self.rx_dma_peripheral, dma_buf, dma_len) {
+                    // The DMA handed `dma_buf` back, so `buffer1` is no
+                    // longer aliased and can be returned.
+                    self.dma_running.set(false);
+                    dma.disable();
+                    self.active.set(false);
+                    self.continuous.set(false);
+                    let buffer2 = self.next_dma_buffer.take().unwrap_or(&mut []);
+                    return Err((err, buffer1, buffer2));
+                }
+            }
 
             // start timer
             self.registers.cr.write(Control::TSTART::SET);
@@ -985,7 +994,7 @@ impl dma::DMAClient for Adc<'_> {
             // get buffer filled with samples from DMA
             let dma_buffer = self.rx_dma.map_or(None, |rx_dma| {
                 self.dma_running.set(false);
-                let dma_buf = rx_dma.abort_transfer();
+                let dma_buf = rx_dma.abort_transfer().map(|(buf, _)| buf);
                 rx_dma.disable();
                 dma_buf
             });
@@ -1022,7 +1031,15 @@ impl dma::DMAClient for Adc<'_> {
                         self.dma_running.set(true);
                         dma.enable();
                         self.rx_length.set(dma_len);
-                        dma.do_transfer(self.rx_dma_peripheral, dma_buf, dma_len);
+                        if dma
+                            .do_transfer(self.rx_dma_peripheral, dma_buf, dma_len)
+                            .is_err()
+                        {
+                            // Keep the buffer, as for a zero length, so that
+                            // `retrieve_buffers` returns it.
+                            self.dma_running.set(false);
+                            self.next_dma_buffer.replace(buf);
+                        }
                     });
                 } else {
                     // if length was zero, just keep the buffer in the takecell
@@ -177,6 +177,25 @@ pub unsafe fn set_ck32source(source: CK32Source) {
         .modify_no_read(control, PowerModeControl::CK32S.val(source as u32));
 }
 
+/// Select whether deep sleep enters RETENTION mode.
+///
+/// If enabled, the next WFI with `SCR.SLEEPDEEP` set only keeps the backup
+/// domain and RAM powered. Execution continues after the WFI once a wakeup
+/// source fires.
+pub unsafe fn set_retention_mode(enabled: bool) {
+    let control = BPM.pmcon.extract();
+    unlock_register(0x1c); // Control
+    BPM.pmcon.modify_no_read(
+        control,
+        PowerModeControl::BKUP::NoPowerSave
+            + if enabled {
+                PowerModeControl::RET::PowerSave
+            } else {
+                PowerModeControl::RET::NoPowerSave
+            },
+    );
+}
+
 unsafe fn unlock_register(register_offset: u32) {
     BPM.unlock
         .write(Unlock::KEY.val(BPM_UNLOCK_KEY) + Unlock::ADDR.val(register_offset));
@@ -6,15 +6,47 @@
 
 use crate::pm;
 
+use core::cell::Cell;
+use core::cmp;
 use core::fmt::Write;
 use cortexm4::{CortexM4, CortexMVariant};
-use kernel::platform::chip::{Chip, InterruptService};
+use kernel::hil::hw_debug::CycleCounter;
+use kernel::platform::chip::{
+    Chip, ClockConfig, CriticalToken, InterruptService, InterruptSnapshot, ResetReason,
+    StateDumpLevel, WakeupSource,
+};
+use kernel::platform::mpu::MPU;
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
 
 pub struct Sam4l<I: InterruptService + 'static> {
     mpu: cortexm4::mpu::MPU,
     userspace_kernel_boundary: cortexm4::syscall::SysCall,
     pub pm: &'static crate::pm::PowerManager,
     interrupt_service: &'static I,
+    /// The interrupt that ended the last sleep, if any.
+    wakeup_interrupt: Cell<Option<u32>>,
+    /// Interrupts masked with `mask_interrupt`, one bit per interrupt number.
+    masked_interrupts: Cell<u128>,
+    /// Cycle counter used to time `service_pending_interrupts`.
+    dwt: cortexm4::dwt::Dwt,
+    /// Whether `enable_cycle_counter` has started the cycle counter.
+    counting: Cell<bool>,
+    /// Last value read from the 32-bit cycle counter and the number of times
+    /// it has wrapped, used to extend it to 64 bits in `cycle_count`.
+    cycle_count_state: Cell<(u32, u32)>,
+    /// Whether `enable_interrupt_profiling` has been called.
+    profiling: Cell<bool>,
+    /// Duration, in CPU cycles, of the last and the longest timed
+    /// `service_pending_interrupts`.
+    service_cycles: Cell<Option<(u32, u32)>>,
+    /// Cause of the last reset, read once when the chip is created.
+    reset_reason: ResetReason,
+    /// Flash controller, whose wait states `restore_clocks` must adjust.
+    flash_controller: OptionalCell<&'static crate::flashcalw::FLASHCALW>,
+    /// Dispatcher servicing all DMA channels at once, see
+    /// `set_dma_dispatcher`.
+    dma_dispatcher: OptionalCell<&'static crate::dma::DMA>,
 }
 
 impl<I: InterruptService + 'static> Sam4l<I> {
@@ -24,8 +56,65 @@ impl<I: InterruptService + 'static> Sam4l<I> {
             userspace_kernel_boundary: cortexm4::syscall::SysCall::new(),
             pm,
             interrupt_service,
+            wakeup_interrupt: Cell::new(None),
+            masked_interrupts: Cell::new(0),
+            dwt: cortexm4::dwt::Dwt::new(),
+            counting: Cell::new(false),
+            cycle_count_state: Cell::new((0, 0)),
+            profiling: Cell::new(false),
+            service_cycles: Cell::new(None),
+            reset_reason: pm::reset_reason(),
+            flash_controller: OptionalCell::empty(),
+            dma_dispatcher: OptionalCell::empty(),
         }
     }
+
+    /// Set the flash controller used by `Chip::restore_clocks`, which
+    /// returns `OFF` until it is set.
+    pub fn set_flash_controller(&self, flash_controller: &'static crate::flashcalw::FLASHCALW) {
+        self.flash_controller.set(flash_controller);
+    }
+
+    /// Service every DMA channel with `dispatcher` whenever any of them
+    /// interrupts, instead of only the channel whose interrupt is dispatched.
+    /// This bounds completion latency when several channels finish at once.
+    /// Channels masked with `mask_interrupt` are skipped.
+    pub fn set_dma_dispatcher(&self, dispatcher: &'static crate::dma::DMA) {
+        self.dma_dispatcher.set(dispatcher);
+    }
+
+    /// Start timing `service_pending_interrupts` with the DWT cycle counter,
+    /// see `Chip::last_service_duration_us`. This enables the debug and trace
+    /// unit, which draws extra power, and leaves the cycle counter running.
+    /// Does nothing if the core has no cycle counter.
+    pub fn enable_interrupt_profiling(&self) {
+        self.enable_cycle_counter();
+        self.profiling.set(self.counting.get());
+    }
+
+    /// Start the DWT cycle counter read by `Chip::cycle_count`. This enables
+    /// the debug and trace unit, which draws extra power. The counter runs
+    /// at the CPU clock and stops while the core sleeps. Does nothing if the
+    /// core has no cycle counter.
+    pub fn enable_cycle_counter(&self) {
+        if !self.counting.get() && self.dwt.is_cycle_counter_present() {
+            self.dwt.start();
+            self.counting.set(true);
+        }
+    }
+
+    /// Convert a number of CPU cycles to microseconds.
+    fn cycles_to_us(&self, cycles: u32) -> u32 {
+        let cycles_per_us = cmp::max(self.pm.get_system_frequency() / 1_000_000, 1);
+        cycles / cycles_per_us
+    }
+
+    /// Record which interrupt ended the sleep that just returned. The kernel
+    /// sleeps with interrupts disabled, so it is still pending in the NVIC.
+    fn record_wakeup(&self) {
+        self.wakeup_interrupt
+            .set(unsafe { cortexm4::nvic::next_pending() });
+    }
 }
 
 /// This struct, when initialized, instantiates all peripheral drivers for the apollo3.
@@ -102,7 +191,7 @@ impl Sam4lDefaultPeripherals {
             i2c1: crate::i2c::I2CHw::new_i2c1(pm),
             i2c2: crate::i2c::I2CHw::new_i2c2(pm),
             i2c3: crate::i2c::I2CHw::new_i2c3(pm),
-            spi: crate::spi::SpiHw::new(pm),
+            spi: crate::spi::SpiHw::new(),
             trng: crate::trng::Trng::new(),
             usart0: crate::usart::USART::new_usart0(pm),
             usart1: crate::usart::USART::new_usart1(pm),
@@ -240,25 +329,59 @@ impl<I: InterruptService + 'static> Chip for Sam4l<I> {
     type UserspaceKernelBoundary = cortexm4::syscall::SysCall;
 
     fn service_pending_interrupts(&self) {
+        // The cycle counter is only 32 bits wide, so this relies on servicing
+        // interrupts taking less than one full turn of it.
+        let start = self.profiling.get().then(|| self.dwt.count() as u32);
+        let mut serviced = false;
         unsafe {
             loop {
-                if let Some(interrupt) = cortexm4::nvic::next_pending() {
-                    match self.interrupt_service.service_interrupt(interrupt) {
-                        true => {}
-                        false => panic!("unhandled interrupt"),
+                let mask = (0, self.masked_interrupts.get());
+                if let Some(interrupt) = cortexm4::nvic::next_pending_with_mask(mask) {
+                    let dispatched = (crate::nvic::PDCA0..=crate::nvic::PDCA15)
+                        .contains(&interrupt)
+                        && self
+                            .dma_dispatcher
+                            .map(|dma| dma.service_all(self.masked_interrupts.get()))
+                            .is_some();
+                    if !dispatched {
+                        match self.interrupt_service.service_interrupt(interrupt) {
+                            true => {}
+                            false => panic!("unhandled interrupt"),
+                        }
                     }
                     let n = cortexm4::nvic::Nvic::new(interrupt);
                     n.clear_pending();
                     n.enable();
+                    serviced = true;
                 } else {
                     break;
                 }
             }
         }
+
+        if let (Some(start), true) = (start, serviced) {
+            let cycles = (self.dwt.count() as u32).wrapping_sub(start);
+            let max = self.service_cycles.get().map_or(0, |(_, max)| max);
+            self.service_cycles
+                .set(Some((cycles, cmp::max(cycles, max))));
+        }
     }
 
     fn has_pending_interrupts(&self) -> bool {
-        unsafe { cortexm4::nvic::has_pending() }
+        let mask = (0, self.masked_interrupts.get());
+        unsafe { cortexm4::nvic::has_pending_with_mask(mask) }
+    }
+
+    fn pending_interrupts(&self, buf: &mut [u32]) -> usize {
+        unsafe { cortexm4::nvic::pending(buf) }
+    }
+
+    fn interrupt_snapshot(&self) -> InterruptSnapshot {
+        let mut snapshot = InterruptSnapshot::default();
+        unsafe {
+            cortexm4::nvic::pending_and_active(&mut snapshot.pending, &mut snapshot.active);
+        }
+        snapshot
     }
 
     fn mpu(&self) -> &cortexm4::mpu::MPU {
@@ -283,6 +406,104 @@ impl<I: InterruptService + 'static> Chip for Sam4l<I> {
         unsafe {
             cortexm4::support::wfi();
         }
+        self.record_wakeup();
+    }
+
+    fn deep_sleep(&self) {
+        unsafe {
+            crate::bpm::set_retention_mode(true);
+            cortexm4::scb::set_sleepdeep();
+            cortexm4::support::wfi();
+            crate::bpm::set_retention_mode(false);
+        }
+        self.record_wakeup();
+    }
+
+    fn device_id(&self, buf: &mut [u8]) -> usize {
+        let serial_num = crate::serial_num::SerialNum::new().get();
+        let len = cmp::min(buf.len(), serial_num.len());
+        buf[..len].copy_from_slice(&serial_num[..len]);
+        len
+    }
+
+    fn last_service_duration_us(&self) -> Option<u32> {
+        self.service_cycles
+            .get()
+            .map(|(last, _)| self.cycles_to_us(last))
+    }
+
+    fn max_service_duration_us(&self) -> Option<u32> {
+        self.service_cycles
+            .get()
+            .map(|(_, max)| self.cycles_to_us(max))
+    }
+
+    fn save_clocks(&self) -> ClockConfig {
+        self.pm.save_clocks()
+    }
+
+    fn restore_clocks(&self, config: &ClockConfig) -> Result<(), ErrorCode> {
+        let flash_controller = self.flash_controller.get().ok_or(ErrorCode::OFF)?;
+        // SAFETY: `change_system_clock` switches the system clock and flash
+        // wait states together, and the PM only accepts configurations
+        // captured by `save_clocks`.
+        unsafe { self.pm.restore_clocks(config, flash_controller) }
+    }
+
+    fn cycle_count(&self) -> u64 {
+        if !self.counting.get() {
+            return 0;
+        }
+        // CYCCNT is 32 bits wide and wraps in under 90 seconds at 48 MHz.
+        // Count the wraps seen between calls to extend it to 64 bits.
+        let low = self.dwt.count() as u32;
+        let (last, mut wraps) = self.cycle_count_state.get();
+        if low < last {
+            wraps = wraps.wrapping_add(1);
+        }
+        self.cycle_count_state.set((low, wraps));
+        (u64::from(wraps) << 32) | u64::from(low)
+    }
+
+    fn mask_interrupt(&self, irq: u32) {
+        if irq < u128::BITS {
+            self.masked_interrupts
+                .set(self.masked_interrupts.get() | 1 << irq);
+            unsafe { cortexm4::nvic::Nvic::new(irq).disable() };
+        }
+    }
+
+    fn unmask_interrupt(&self, irq: u32) {
+        if irq < u128::BITS {
+            self.masked_interrupts
+                .set(self.masked_interrupts.get() & !(1 << irq));
+            unsafe { cortexm4::nvic::Nvic::new(irq).enable() };
+        }
+    }
+
+    fn last_wakeup_source(&self) -> Option<WakeupSource> {
+        use crate::nvic;
+        self.wakeup_interrupt
+            .get()
+            .map(|interrupt| match interrupt {
+                nvic::ASTALARM..=nvic::WDT | nvic::TC00..=nvic::TC12 => WakeupSource::Timer,
+                nvic::GPIO0..=nvic::GPIO11 | nvic::EIC1..=nvic::EIC8 => WakeupSource::Gpio,
+                nvic::USART0..=nvic::USART3 => WakeupSource::Uart,
+                nvic::TWIM0..=nvic::TWIS1 | nvic::TWIM2 | nvic::TWIM3 => WakeupSource::I2c,
+                nvic::SPI => WakeupSource::Spi,
+                nvic::USBC => WakeupSource::Usb,
+                nvic::PDCA0..=nvic::PDCA15 => WakeupSource::Dma,
+                other => WakeupSource::Other(other),
+            })
+    }
+
+    fn reset_reason(&self) -> ResetReason {
+        self.reset_reason
+    }
+
+    fn data_barrier(&self) {
+        // The SAM4L has no data cache, but the core's write buffer must drain.
+        cortexm4::support::dsb();
     }
 
     unsafe fn atomic<F, R>(&self, f: F) -> R
@@ -292,7 +513,35 @@ impl<I: InterruptService + 'static> Chip for Sam4l<I> {
         cortexm4::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            cortexm4::support::disable_interrupts(),
+            cortexm4::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, writer: &mut dyn Write) {
         CortexM4::print_cortexm_state(writer);
+        if let Some(fault_addr) = cortexm4::mpu_fault_address() {
+            self.mpu.describe_fault(fault_addr, writer);
+        }
+    }
+
+    unsafe fn print_state_at_level(&self, writer: &mut dyn Write, level: StateDumpLevel) {
+        match level {
+            StateDumpLevel::Minimal => cortexm4::print_cortexm_fault_status(writer),
+            StateDumpLevel::Normal => self.print_state(writer),
+            StateDumpLevel::Verbose => {
+                self.print_state(writer);
+
+                let mut pending = [0; 8];
+                let count = self.pending_interrupts(&mut pending);
+                let _ = writer.write_fmt(format_args!("Pending interrupts: "));
+                for interrupt in &pending[..count] {
+                    let _ = writer.write_fmt(format_args!("{} ", interrupt));
+                }
+                let _ = writer.write_fmt(format_args!("\r\n"));
+            }
+        }
     }
 }
@@ -8,11 +8,16 @@ use crate::pm;
 use core::cell::Cell;
 use core::cmp;
 use core::sync::atomic;
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+use kernel::hil::time::{self, Alarm, ConvertTicks};
 use kernel::utilities::cells::VolatileCell;
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::interfaces::{Readable, Writeable};
-use kernel::utilities::registers::{register_bitfields, ReadOnly, ReadWrite, WriteOnly};
+use kernel::utilities::registers::{
+    register_bitfields, FieldValue, ReadOnly, ReadWrite, WriteOnly,
+};
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 
 /// Memory registers for a DMA channel. Section 16.6.1 of the datasheet.
 #[repr(C)]
@@ -100,6 +105,12 @@ const DMA_CHANNEL_SIZE: usize = 0x40;
 /// active.
 static NUM_ENABLED: atomic::AtomicUsize = atomic::AtomicUsize::new(0);
 
+/// The number of DMA channels that are currently enabled. The PDCA clocks are
+/// running exactly when this is non-zero.
+pub fn active_channel_count() -> usize {
+    NUM_ENABLED.load(atomic::Ordering::Relaxed)
+}
+
 /// The DMA channel number.
 ///
 /// Each channel transfers data between memory and a
@@ -173,6 +184,95 @@ pub enum DMAPeripheral {
     LCDCA_ABMDR_TX = 38,
 }
 
+/// Direction of a transfer between memory and a peripheral, used to pick a
+/// `DMAPeripheral`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DMADirection {
+    /// From the peripheral to memory, the `*_RX` peripherals.
+    Rx,
+    /// From memory to the peripheral, the `*_TX` peripherals.
+    Tx,
+}
+
+impl DMAPeripheral {
+    /// Whether this peripheral is read from (`*_RX`) or written to (`*_TX`).
+    pub const fn direction(&self) -> DMADirection {
+        if (*self as u8) < (Self::USART0_TX as u8) {
+            DMADirection::Rx
+        } else {
+            DMADirection::Tx
+        }
+    }
+
+    /// Pick the variant for the direction from a pair of RX and TX variants.
+    const fn with_direction(
+        dir: DMADirection,
+        rx: DMAPeripheral,
+        tx: DMAPeripheral,
+    ) -> DMAPeripheral {
+        match dir {
+            DMADirection::Rx => rx,
+            DMADirection::Tx => tx,
+        }
+    }
+
+    /// The peripheral for USART `n`, or `None` if there is no such USART.
+    pub const fn for_usart(n: usize, dir: DMADirection) -> Option<DMAPeripheral> {
+        match n {
+            0 => Some(Self::with_direction(dir, Self::USART0_RX, Self::USART0_TX)),
+            1 => Some(Self::with_direction(dir, Self::USART1_RX, Self::USART1_TX)),
+            2 => Some(Self::with_direction(dir, Self::USART2_RX, Self::USART2_TX)),
+            3 => Some(Self::with_direction(dir, Self::USART3_RX, Self::USART3_TX)),
+            _ => None,
+        }
+    }
+
+    /// The peripheral for the SPI controller.
+    pub const fn for_spi(dir: DMADirection) -> DMAPeripheral {
+        Self::with_direction(dir, Self::SPI_RX, Self::SPI_TX)
+    }
+
+    /// The peripheral for I2C controller (TWIM) `n`, or `None` if there is no
+    /// such controller.
+    pub const fn for_twim(n: usize, dir: DMADirection) -> Option<DMAPeripheral> {
+        match n {
+            0 => Some(Self::with_direction(dir, Self::TWIM0_RX, Self::TWIM0_TX)),
+            1 => Some(Self::with_direction(dir, Self::TWIM1_RX, Self::TWIM1_TX)),
+            2 => Some(Self::with_direction(dir, Self::TWIM2_RX, Self::TWIM2_TX)),
+            3 => Some(Self::with_direction(dir, Self::TWIM3_RX, Self::TWIM3_TX)),
+            _ => None,
+        }
+    }
+
+    /// The peripheral for I2C target (TWIS) `n`, or `None` if there is no such
+    /// target.
+    pub const fn for_twis(n: usize, dir: DMADirection) -> Option<DMAPeripheral> {
+        match n {
+            0 => Some(Self::with_direction(dir, Self::TWIS0_RX, Self::TWIS0_TX)),
+            1 => Some(Self::with_direction(dir, Self::TWIS1_RX, Self::TWIS1_TX)),
+            _ => None,
+        }
+    }
+
+    /// The peripheral for channel `channel` of the I2S controller (IISC), or
+    /// `None` if there is no such channel.
+    pub const fn for_iisc(channel: usize, dir: DMADirection) -> Option<DMAPeripheral> {
+        match channel {
+            0 => Some(Self::with_direction(
+                dir,
+                Self::IISC_CH0_RX,
+                Self::IISC_CH0_TX,
+            )),
+            1 => Some(Self::with_direction(
+                dir,
+                Self::IISC_CH1_RX,
+                Self::IISC_CH1_TX,
+            )),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(u8)]
 pub enum DMAWidth {
@@ -184,16 +284,202 @@ pub enum DMAWidth {
     Width32Bit = 2,
 }
 
+impl DMAWidth {
+    /// The number of bytes moved by a single DMA transfer of this width.
+    pub fn bytes(&self) -> usize {
+        match self {
+            DMAWidth::Width8Bit => 1,
+            DMAWidth::Width16Bit => 2,
+            DMAWidth::Width32Bit => 4,
+        }
+    }
+
+    /// Whether `buf` can be used for a transfer of this width, i.e. whether
+    /// its start address is aligned to the width. A misaligned transfer
+    /// silently corrupts data.
+    pub fn is_aligned(&self, buf: &[u8]) -> bool {
+        buf.as_ptr() as usize % self.bytes() == 0
+    }
+}
+
+/// What starts each transfer of a channel.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DMATrigger {
+    /// Each transfer is started by a request from the channel's peripheral,
+    /// e.g. when a USART has received a byte. This is the default.
+    OnPeripheralRequest,
+    /// Each transfer is started by an event routed to the channel through the
+    /// Peripheral Event System, e.g. a timer compare event. Only channels 0 to
+    /// 3 are event users of the Peripheral Event Controller, so this is not
+    /// supported on the other channels.
+    OnExternalEvent,
+}
+
+/// Returns the widest `DMAWidth` that can safely be used to transfer `buf`:
+/// the start address of `buf` is aligned to it and the length of `buf` is a
+/// multiple of it.
+pub fn max_safe_width(buf: &[u8]) -> DMAWidth {
+    [DMAWidth::Width32Bit, DMAWidth::Width16Bit]
+        .into_iter()
+        .find(|width| width.is_aligned(buf) && buf.len() % width.bytes() == 0)
+        .unwrap_or(DMAWidth::Width8Bit)
+}
+
+/// Reverse the order of the bytes within each unit of `width` in `buf`.
+///
+/// This converts 16 or 32-bit samples between little endian and the big
+/// endian (network) byte order some peripherals expect. Trailing bytes that
+/// do not make up a whole unit are left as they are, and 8-bit widths are a
+/// no-op.
+pub fn swap_buffer_in_place(width: DMAWidth, buf: &mut [u8]) {
+    if width != DMAWidth::Width8Bit {
+        buf.chunks_exact_mut(width.bytes())
+            .for_each(<[u8]>::reverse);
+    }
+}
+
+/// The kind of alarm used to time out DMA transfers, see
+/// `DMAChannel::set_timeout_alarm`. This matches the AST and virtual alarms
+/// built on top of it.
+pub type DMATimeoutAlarm = dyn Alarm<'static, Frequency = time::Freq16KHz, Ticks = time::Ticks32>;
+
+/// State of a channel's current transfer, see `DMAChannel::transfer_status`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TransferStatus {
+    /// The transfer, including any pending reload, still has `remaining`
+    /// bytes to move.
+    InProgress { remaining: usize },
+    /// The transfer and any pending reload have completed.
+    Complete,
+    /// The transfer was stopped by a bus error.
+    Error,
+}
+
 pub struct DMAChannel {
     registers: StaticRef<DMARegisters>,
+    num: DMAChannelNum,
     client: OptionalCell<&'static dyn DMAClient>,
     width: Cell<DMAWidth>,
+    trigger: Cell<DMATrigger>,
+    /// Whether a peripheral has been written to `psr` since the last `reset`.
+    peripheral_set: Cell<bool>,
     enabled: Cell<bool>,
     buffer: TakeCell<'static, [u8]>,
+    reload_buffer: TakeCell<'static, [u8]>,
+    /// Length, in units of `width`, of the transfer using `buffer`.
+    len: Cell<usize>,
+    /// Offset, in bytes, into `buffer` at which the transfer starts.
+    offset: Cell<usize>,
+    /// Length, in units of `width`, of the transfer using `reload_buffer`.
+    reload_len: Cell<usize>,
+    /// Whether a ring transfer started with `start_ring_transfer` is running.
+    ring: Cell<bool>,
+    /// Granularity, in units of `width`, of `progress` callbacks. Zero if
+    /// progress interrupts are disabled.
+    progress_every: Cell<usize>,
+    /// Number of units of the current transfer that have been programmed into
+    /// the channel so far, when it is split up for progress interrupts.
+    progress_queued: Cell<usize>,
+    /// Length, in units of `width`, of the most recently programmed segment of
+    /// a transfer that is split up for progress interrupts.
+    progress_last: Cell<usize>,
+    /// Size, in units of `width`, of the chunks transfers are split into. Zero
+    /// if transfers are not split into chunks.
+    chunk_size: Cell<usize>,
+    /// Number of units of the current transfer that have been programmed into
+    /// the channel so far, when it is split into chunks.
+    chunk_next: Cell<usize>,
+    /// The other channel of a relay started with `relay`, if this channel is
+    /// part of one.
+    relay_partner: OptionalCell<&'static DMAChannel>,
+    /// Whether a ping-pong transfer started with `start_pingpong` is running.
+    pingpong: Cell<bool>,
+    /// Which buffer of a ping-pong transfer, 0 or 1, is in `buffer`.
+    pingpong_which: Cell<usize>,
+    /// Bytes moved by completed transfers and segments since the last
+    /// `start_transfer`, see `bytes_transferred`.
+    bytes_transferred: Cell<usize>,
+    /// Buffer of the transfer aborted by `disable`, see
+    /// `take_disabled_buffer`.
+    disabled_buffer: TakeCell<'static, [u8]>,
+    /// Bytes transferred by the transfer aborted by `disable`.
+    disabled_bytes: Cell<usize>,
+    /// Alarm used by `start_transfer_with_timeout`.
+    timeout_alarm: OptionalCell<&'static DMATimeoutAlarm>,
+    /// Whether the current transfer was started with a timeout that has not
+    /// expired yet.
+    timeout_pending: Cell<bool>,
+    /// Deferred call registered for this channel by `DMADeferredDone`.
+    deferred_done: OptionalCell<&'static DMADeferredDone>,
+    /// Whether `transfer_done` is delivered through `deferred_done`, see
+    /// `set_deferred_callbacks`.
+    defer_callbacks: Cell<bool>,
+    /// Whether outgoing data is byte swapped, see `set_byte_swap`.
+    byte_swap: Cell<bool>,
+    /// Start and end address of the data `prepare_transfer_offset` swapped
+    /// for a transfer that has not completed, so that retrying it does not
+    /// swap the data back.
+    swapped: Cell<Option<(usize, usize)>>,
 }
 
 pub trait DMAClient {
     fn transfer_done(&self, pid: DMAPeripheral);
+
+    /// Called instead of `transfer_done` when the channel stopped because of a
+    /// transfer error, e.g. a bus error accessing the buffer. As after
+    /// `transfer_done`, the buffer and the number of bytes transferred before
+    /// the error can be retrieved with `abort_transfer` (or with
+    /// `stop_ring_transfer` for a ring transfer), so the client can retry or
+    /// report the failure.
+    ///
+    /// The default implementation calls `transfer_done`.
+    fn transfer_error(&self, pid: DMAPeripheral) {
+        self.transfer_done(pid);
+    }
+
+    /// Called when the channel has finished the current segment of a transfer
+    /// and moved on to the segment queued with `prepare_reload`. `buf` is the
+    /// buffer of the segment that just finished. The client may queue the
+    /// next segment by calling `prepare_reload` again from this callback.
+    ///
+    /// `transfer_done` is still called once the last segment finishes.
+    fn reload_needed(&self, _pid: DMAPeripheral, _buf: &'static mut [u8]) {}
+
+    /// Called each time a ring transfer started with `start_ring_transfer`
+    /// wraps around to the start of its buffer.
+    fn ring_wrapped(&self, _pid: DMAPeripheral) {}
+
+    /// Called periodically during a transfer when progress interrupts have
+    /// been enabled with `enable_progress_interrupts`. `bytes_done` is the
+    /// number of bytes of the current transfer moved so far.
+    fn progress(&self, _pid: DMAPeripheral, _bytes_done: usize) {}
+
+    /// Called instead of `transfer_done` when a transfer started with
+    /// `start_transfer_with_timeout` did not finish in time. The channel has
+    /// been stopped, and as after `transfer_done` the buffer and the number of
+    /// bytes transferred can be retrieved with `abort_transfer`.
+    ///
+    /// The default implementation calls `transfer_error`.
+    fn transfer_timeout(&self, pid: DMAPeripheral) {
+        self.transfer_error(pid);
+    }
+
+    /// Called during a ping-pong transfer started with `start_pingpong` each
+    /// time the channel finishes one buffer and switches to the other. `buf`
+    /// is the finished buffer and `which` tells which one it is, alternating
+    /// between 0 and 1 starting with `buf_a`. The client must queue the next
+    /// buffer, e.g. `buf` after refilling it, with `prepare_reload` before the
+    /// other one finishes.
+    fn buffer_ready(&self, _pid: DMAPeripheral, _which: usize, _buf: &'static mut [u8]) {}
+
+    /// Called when a ping-pong transfer stopped because the channel finished
+    /// a buffer before the client queued the next one. The buffer that
+    /// finished can be retrieved with `abort_transfer`.
+    ///
+    /// The default implementation calls `transfer_error`.
+    fn buffer_underrun(&self, pid: DMAPeripheral) {
+        self.transfer_error(pid);
+    }
 }
 
 impl DMAChannel {
@@ -204,11 +490,68 @@ impl DMAChannel {
                     (DMA_BASE_ADDR + (channel as usize) * DMA_CHANNEL_SIZE) as *const DMARegisters,
                 )
             },
+            num: channel,
             client: OptionalCell::empty(),
             width: Cell::new(DMAWidth::Width8Bit),
+            trigger: Cell::new(DMATrigger::OnPeripheralRequest),
+            peripheral_set: Cell::new(false),
             enabled: Cell::new(false),
             buffer: TakeCell::empty(),
+            reload_buffer: TakeCell::empty(),
+            len: Cell::new(0),
+            offset: Cell::new(0),
+            reload_len: Cell::new(0),
+            ring: Cell::new(false),
+            progress_every: Cell::new(0),
+            progress_queued: Cell::new(0),
+            progress_last: Cell::new(0),
+            chunk_size: Cell::new(0),
+            chunk_next: Cell::new(0),
+            relay_partner: OptionalCell::empty(),
+            pingpong: Cell::new(false),
+            pingpong_which: Cell::new(0),
+            bytes_transferred: Cell::new(0),
+            disabled_buffer: TakeCell::empty(),
+            disabled_bytes: Cell::new(0),
+            timeout_alarm: OptionalCell::empty(),
+            timeout_pending: Cell::new(false),
+            deferred_done: OptionalCell::empty(),
+            defer_callbacks: Cell::new(false),
+            byte_swap: Cell::new(false),
+            swapped: Cell::new(None),
+        }
+    }
+
+    /// Change the width of subsequent transfers, checking that `buf` (the
+    /// buffer the caller intends to transfer) is aligned to it. Returns `INVAL`
+    /// and leaves the width unchanged if it is not.
+    pub fn set_width_checked(&self, width: DMAWidth, buf: &[u8]) -> Result<(), ErrorCode> {
+        if !width.is_aligned(buf) {
+            return Err(ErrorCode::INVAL);
+        }
+        self.width.set(width);
+        Ok(())
+    }
+
+    /// Select what starts each transfer of subsequent transfers.
+    ///
+    /// Returns `NOSUPPORT` if `trigger` is `OnExternalEvent` and this channel
+    /// cannot receive events, rather than silently using request triggering.
+    pub fn set_trigger(&self, trigger: DMATrigger) -> Result<(), ErrorCode> {
+        if trigger == DMATrigger::OnExternalEvent && self.num as usize > 3 {
+            return Err(ErrorCode::NOSUPPORT);
         }
+        self.trigger.set(trigger);
+        Ok(())
+    }
+
+    /// The mode register value for the configured width and trigger.
+    fn mode(&self) -> FieldValue<u32, Mode::Register> {
+        let trigger = match self.trigger.get() {
+            DMATrigger::OnPeripheralRequest => Mode::ETRIG::StartOnRequest,
+            DMATrigger::OnExternalEvent => Mode::ETRIG::StartOnEvent,
+        };
+        Mode::SIZE.val(self.width.get() as u32) + trigger
     }
 
     pub fn initialize(&self, client: &'static dyn DMAClient, width: DMAWidth) {
@@ -216,6 +559,19 @@ impl DMAChannel {
         self.width.set(width);
     }
 
+    /// Replace the client set by `initialize`, e.g. when a pooled channel is
+    /// handed to a different driver. The channel must not be in the middle of
+    /// a transfer. The width and any other configuration persist; the new
+    /// client should set them if it needs different values.
+    pub fn set_client(&self, client: &'static dyn DMAClient) {
+        debug_assert!(
+            !(self.is_transfer_active()
+                && matches!(self.transfer_status(), TransferStatus::InProgress { .. })),
+            "DMA client changed during a transfer"
+        );
+        self.client.set(client);
+    }
+
     pub fn enable(&self) {
         pm::enable_clock(pm::Clock::HSB(pm::HSBClock::PDCA));
         pm::enable_clock(pm::Clock::PBB(pm::PBBClock::PDCA));
@@ -232,27 +588,403 @@ impl DMAChannel {
         }
     }
 
-    pub fn disable(&self) {
+    /// Disable the channel, and the PDCA clock if no other channel is
+    /// enabled.
+    ///
+    /// Any transfer still in progress is aborted first, while the clock is
+    /// running, so that it does not strand its buffer inside the channel. The
+    /// buffer and the number of bytes transferred, as returned by
+    /// `abort_transfer`, can be retrieved with `take_disabled_buffer`. For a
+    /// ring transfer the number of bytes is 0. A pending reload buffer can
+    /// still be retrieved with `abort_reload`.
+    ///
+    /// Only one disabled buffer is kept. If an earlier `disable` left one that
+    /// was not retrieved with `take_disabled_buffer` and this call aborts
+    /// another transfer, the earlier buffer is returned instead of dropped.
+    pub fn disable(&self) -> Option<(&'static mut [u8], usize)> {
+        let mut displaced = None;
         if self.enabled.get() {
+            let aborted = if self.ring.get() {
+                self.stop_ring_transfer().map(|buf| (buf, 0))
+            } else {
+                self.abort_transfer()
+            };
+            if let Some((buf, bytes)) = aborted {
+                displaced = self.take_disabled_buffer();
+                self.disabled_buffer.replace(buf);
+                self.disabled_bytes.set(bytes);
+            }
+            self.registers.cr.write(Control::TDIS::SET);
+
             let num_enabled = NUM_ENABLED.fetch_sub(1, atomic::Ordering::Relaxed);
             if num_enabled == 1 {
                 pm::disable_clock(pm::Clock::HSB(pm::HSBClock::PDCA));
                 pm::disable_clock(pm::Clock::PBB(pm::PBBClock::PDCA));
             }
-            self.registers.cr.write(Control::TDIS::SET);
             self.enabled.set(false);
         }
+        displaced
+    }
+
+    /// Return the buffer of the transfer aborted by the last `disable`, along
+    /// with the number of bytes it transferred, if there was one.
+    pub fn take_disabled_buffer(&self) -> Option<(&'static mut [u8], usize)> {
+        self.disabled_buffer
+            .take()
+            .map(|buf| (buf, self.disabled_bytes.get()))
     }
 
     pub fn is_enabled(&self) -> bool {
         self.enabled.get()
     }
 
+    /// Set the NVIC priority of the channel's interrupt, lower values being
+    /// more urgent. Each PDCA channel has its own interrupt line, so this only
+    /// affects this channel. The SAM4L implements the 4 most significant bits
+    /// of `priority`.
+    ///
+    /// Note that Tock interrupt handlers only defer work to the kernel loop,
+    /// which services pending interrupts in order of their number regardless
+    /// of priority. For channels, that means lower numbered channels first,
+    /// see `DMAChannelAllocator`.
+    pub fn set_interrupt_priority(&self, priority: u8) {
+        self.nvic().set_priority(priority);
+    }
+
+    /// The NVIC line of the channel's interrupt.
+    /// The NVIC interrupt number of the channel.
+    fn nvic_number(&self) -> u32 {
+        crate::nvic::PDCA0 + self.num as u32
+    }
+
+    fn nvic(&self) -> cortexm4::nvic::Nvic {
+        unsafe { cortexm4::nvic::Nvic::new(self.nvic_number()) }
+    }
+
+    /// Whether the channel has an enabled interrupt condition that
+    /// `handle_interrupt` would service.
+    pub fn interrupt_pending(&self) -> bool {
+        self.registers.imr.get() & self.registers.isr.get() != 0
+    }
+
+    /// The width of the channel's transfers, as set by `initialize` or
+    /// `set_width_checked`.
+    pub fn width(&self) -> DMAWidth {
+        self.width.get()
+    }
+
+    /// The peripheral the channel transfers to or from, or `None` if no
+    /// transfer has been prepared since the last `reset`. A driver that uses
+    /// one channel for both directions can check this before starting a
+    /// transfer.
+    pub fn peripheral(&self) -> Option<DMAPeripheral> {
+        self.peripheral_set.get().then(|| self.registers.psr.get())
+    }
+
+    fn set_peripheral(&self, pid: DMAPeripheral) {
+        self.registers.psr.set(pid);
+        self.peripheral_set.set(true);
+    }
+
+    /// Whether the channel is enabled for transfers, i.e. a transfer has been
+    /// started with `start_transfer` and not disabled since. The channel stays
+    /// enabled after the transfer completes; use `poll_complete` to check for
+    /// completion.
+    pub fn is_transfer_active(&self) -> bool {
+        self.registers.sr.is_set(Status::TEN)
+    }
+
+    /// Whether the current transfer, including any pending reload, has
+    /// completed. This allows waiting for a transfer by polling, e.g. early
+    /// during boot before interrupts are enabled.
+    ///
+    /// The PDCA interrupt status register is not cleared by reading it (TRC
+    /// is only cleared by writing the transfer counters), so this does not
+    /// interfere with `handle_interrupt`. If the TRC interrupt is enabled it
+    /// will still fire and call the client's `transfer_done`.
+    pub fn poll_complete(&self) -> bool {
+        self.registers.isr.is_set(Interrupt::TRC)
+    }
+
+    /// The state of the current transfer, read directly from the channel's
+    /// status and counter registers. This lets the channel be polled, e.g.
+    /// by an async executor, without a `DMAClient`. As with `poll_complete`,
+    /// reading the status does not affect `handle_interrupt`, so a registered
+    /// client is still called as usual.
+    ///
+    /// A channel that has not been given a transfer reports `Complete`.
+    pub fn transfer_status(&self) -> TransferStatus {
+        if self.registers.isr.is_set(Interrupt::TERR) {
+            TransferStatus::Error
+        } else if self.registers.isr.is_set(Interrupt::TRC) {
+            TransferStatus::Complete
+        } else {
+            let units = self.registers.tcr.read(TransferCounter::TCV)
+                + self.registers.tcrr.read(TransferCounter::TCV);
+            TransferStatus::InProgress {
+                remaining: units as usize * self.width.get().bytes(),
+            }
+        }
+    }
+
+    /// Call the client's `progress` callback roughly every `every` bytes of
+    /// subsequent transfers. Passing zero disables progress interrupts.
+    ///
+    /// Transfers are split into segments of `every` bytes that are chained
+    /// using the reload registers, so `every` must be a multiple of the
+    /// transfer width (`INVAL` otherwise) and `prepare_reload` cannot be used
+    /// while such a transfer is running. Returns `BUSY` if a transfer is in
+    /// progress, and `NOSUPPORT` while the channel is in ring mode, as ring
+    /// transfers already use the reload registers.
+    pub fn enable_progress_interrupts(&self, every: usize) -> Result<(), ErrorCode> {
+        if self.ring.get() || (every != 0 && self.chunk_size.get() != 0) {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+        if self.buffer.is_some() {
+            return Err(ErrorCode::BUSY);
+        }
+        let width = self.width.get().bytes();
+        if every % width != 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        self.progress_every.set(every / width);
+        Ok(())
+    }
+
+    /// Split subsequent transfers into chunks of `units` transfers (of the
+    /// configured width) each. Passing zero transfers the whole buffer at
+    /// once, which is the default.
+    ///
+    /// Each chunk is only started from `handle_interrupt` once the previous
+    /// one has finished, instead of being queued in the reload registers.
+    /// This leaves gaps in which the bus and the CPU are free for more
+    /// important work, e.g. during a large flash dump. Smaller chunks shorten
+    /// these latencies but cost an interrupt per chunk and make the transfer
+    /// as a whole take longer.
+    ///
+    /// Returns `BUSY` while a transfer is in progress, and `NOSUPPORT` if
+    /// progress interrupts are enabled, as those split transfers as well.
+    pub fn set_chunk_size(&self, units: usize) -> Result<(), ErrorCode> {
+        if units != 0 && self.progress_every.get() != 0 {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+        if self.buffer.is_some() {
+            return Err(ErrorCode::BUSY);
+        }
+        self.chunk_size.set(units);
+        Ok(())
+    }
+
+    /// Whether the current transfer is split into chunks.
+    fn chunking_active(&self) -> bool {
+        self.chunk_next.get() != 0
+    }
+
+    /// Program the next chunk of a transfer that is split into chunks. The
+    /// previous chunk must have finished, so the chunk starts right away.
+    fn start_next_chunk(&self) {
+        let next = self.chunk_next.get();
+        let chunk = cmp::min(self.chunk_size.get(), self.len.get() - next);
+
+        let offset = self.offset.get() + next * self.width.get().bytes();
+        self.buffer.map(|buf| {
+            self.registers.marr.write(
+                MemoryAddressReload::MARV.val(core::ptr::from_ref::<u8>(&buf[offset]) as u32),
+            );
+        });
+        self.registers
+            .tcrr
+            .write(TransferCounter::TCV.val(chunk as u32));
+        self.chunk_next.set(next + chunk);
+    }
+
+    /// Whether the current transfer is split into segments to generate
+    /// progress interrupts.
+    fn progress_active(&self) -> bool {
+        self.progress_queued.get() != 0
+    }
+
+    /// Queue the next segment of a transfer that is split up for progress
+    /// interrupts into the reload registers, if any is left.
+    fn queue_progress_segment(&self) {
+        let queued = self.progress_queued.get();
+        let segment = cmp::min(self.progress_every.get(), self.len.get() - queued);
+        if segment == 0 {
+            return;
+        }
+
+        let offset = self.offset.get() + queued * self.width.get().bytes();
+        self.buffer.map(|buf| {
+            self.registers.marr.write(
+                MemoryAddressReload::MARV.val(core::ptr::from_ref::<u8>(&buf[offset]) as u32),
+            );
+        });
+        self.registers
+            .tcrr
+            .write(TransferCounter::TCV.val(segment as u32));
+
+        self.progress_queued.set(queued + segment);
+        self.progress_last.set(segment);
+        self.registers.ier.write(Interrupt::RCZ::SET);
+    }
+
+    /// Queue another lap of the ring transfer's buffer in the reload
+    /// registers.
+    fn queue_ring_lap(&self) {
+        self.buffer.map(|buf| {
+            self.registers
+                .marr
+                .write(MemoryAddressReload::MARV.val(buf.as_ptr() as u32));
+        });
+        self.registers
+            .tcrr
+            .write(TransferCounter::TCV.val(self.len.get() as u32));
+    }
+
     pub fn handle_interrupt(&self) {
+        let channel = self.registers.psr.get();
+
+        // A transfer error stops the channel, whatever kind of transfer it was
+        // running.
+        if self.registers.imr.is_set(Interrupt::TERR) && self.registers.isr.is_set(Interrupt::TERR)
+        {
+            self.registers
+                .idr
+                .write(Interrupt::TERR::SET + Interrupt::TRC::SET + Interrupt::RCZ::SET);
+            self.registers.cr.write(Control::ECLR::SET);
+
+            // Leave the buffer with this channel so that `abort_transfer`
+            // returns it, even if it was part of a relay.
+            if let Some(partner) = self.relay_partner.take() {
+                partner.relay_partner.clear();
+            }
+            self.cancel_timeout();
+
+            self.client.map(|client| client.transfer_error(channel));
+            return;
+        }
+
+        // During a ring transfer, RCZ means the channel has finished the
+        // buffer and started over on the copy queued in the reload registers.
+        if self.ring.get() {
+            if self.registers.imr.is_set(Interrupt::RCZ)
+                && self.registers.isr.is_set(Interrupt::RCZ)
+            {
+                // If the channel also finished that copy before we serviced
+                // the interrupt, queueing another one restarts it.
+                self.queue_ring_lap();
+                self.count_transferred(self.len.get());
+                self.client.map(|client| client.ring_wrapped(channel));
+            }
+            return;
+        }
+
+        // A transfer split into chunks continues once the previous chunk is
+        // done, until all of it has been programmed.
+        if self.chunking_active()
+            && self.registers.isr.is_set(Interrupt::TRC)
+            && self.chunk_next.get() < self.len.get()
+        {
+            self.start_next_chunk();
+            return;
+        }
+
+        // While relaying, a finished transfer hands the buffer over to the
+        // other channel instead of returning it to the client.
+        if let Some(partner) = self.relay_partner.get() {
+            self.registers
+                .idr
+                .write(Interrupt::TERR::SET + Interrupt::TRC::SET + Interrupt::RCZ::SET);
+            let len = self.len.get();
+            self.len.set(0);
+            self.swapped.set(None);
+            if let Some(buf) = self.buffer.take() {
+                let len = cmp::min(len, partner.capacity(buf));
+                // Both channels are idle and enabled while relaying. Should
+                // the partner refuse the buffer anyway, keep it here so that
+                // `abort_transfer` still returns it.
+                if let Err((_, buf)) = partner.do_transfer(partner.registers.psr.get(), buf, len) {
+                    self.buffer.replace(buf);
+                }
+            }
+            return;
+        }
+
+        // During a transfer split up for progress interrupts, RCZ means the
+        // channel has finished a segment and started on the queued one.
+        if self.progress_active()
+            && self.registers.imr.is_set(Interrupt::RCZ)
+            && self.registers.isr.is_set(Interrupt::RCZ)
+        {
+            self.registers.idr.write(Interrupt::RCZ::SET);
+
+            let done = self.progress_queued.get() - self.progress_last.get();
+            self.client
+                .map(|client| client.progress(channel, done * self.width.get().bytes()));
+            self.queue_progress_segment();
+
+            // As with reloads, the running segment may also have finished
+            // already, in which case queueing another one restarted it.
+            if !self.registers.isr.is_set(Interrupt::TRC) {
+                return;
+            }
+        }
+
+        // RCZ is only enabled while a reload is pending, so if it is set the
+        // PDCA has moved the reload segment into the current registers and the
+        // segment in `buffer` is finished.
+        if self.registers.imr.is_set(Interrupt::RCZ) && self.registers.isr.is_set(Interrupt::RCZ) {
+            self.registers.idr.write(Interrupt::RCZ::SET);
+
+            self.count_transferred(self.len.get());
+            let finished = self.buffer.take();
+            if let Some(buf) = self.reload_buffer.take() {
+                self.buffer.replace(buf);
+                self.len.set(self.reload_len.get());
+                self.offset.set(0);
+            }
+            if let Some(buf) = finished {
+                if self.pingpong.get() {
+                    let which = self.pingpong_which.get();
+                    self.pingpong_which.set(which ^ 1);
+                    self.client
+                        .map(|client| client.buffer_ready(channel, which, buf));
+                } else {
+                    self.client.map(|client| client.reload_needed(channel, buf));
+                }
+            }
+
+            // The reload segment may have also completed before we serviced
+            // the interrupt. Writing a new reload (e.g. from `reload_needed`)
+            // clears TRC, so only report completion if the channel is still
+            // done now.
+            if !self.registers.isr.is_set(Interrupt::TRC) {
+                return;
+            }
+        }
+
         self.registers
             .idr
             .write(Interrupt::TERR::SET + Interrupt::TRC::SET + Interrupt::RCZ::SET);
-        let channel = self.registers.psr.get();
+        self.progress_queued.set(0);
+        self.chunk_next.set(0);
+        self.cancel_timeout();
+        self.count_transferred(self.len.get());
+        self.swapped.set(None);
+
+        // A ping-pong transfer only ends when the client did not queue the
+        // next buffer in time.
+        if self.pingpong.replace(false) {
+            self.client.map(|client| client.buffer_underrun(channel));
+            return;
+        }
+
+        if self.defer_callbacks.get() {
+            if let Some(deferred) = self.deferred_done.get() {
+                deferred.schedule(self);
+                return;
+            }
+        }
 
         self.client.map(|client| {
             client.transfer_done(channel);
@@ -260,57 +992,980 @@ impl DMAChannel {
     }
 
     pub fn start_transfer(&self) {
+        self.bytes_transferred.set(0);
+
+        // A zero-length transfer has nothing to move, so the channel is not
+        // enabled. Both transfer counters are zero, which sets TRC, so pending
+        // the channel's interrupt completes the transfer through
+        // `handle_interrupt` from the kernel loop, rather than calling the
+        // client from within this call.
+        if self.len.get() == 0 && self.buffer.is_some() && !self.ring.get() {
+            self.nvic().set_pending();
+            return;
+        }
+
         self.registers.cr.write(Control::TEN::SET);
     }
 
-    pub fn prepare_transfer(&self, pid: DMAPeripheral, buf: &'static mut [u8], mut len: usize) {
-        // TODO(alevy): take care of zero length case
+    /// Number of bytes moved since the last `start_transfer`, counting each
+    /// completed transfer, reload segment, and ring buffer pass in full. Unlike
+    /// `transfer_counter`, this keeps counting across reloads and ring wraps,
+    /// e.g. to compute the playback position of a stream. The count wraps
+    /// around on overflow.
+    pub fn bytes_transferred(&self) -> usize {
+        self.bytes_transferred.get()
+    }
 
-        let maxlen = buf.len()
-            / match self.width.get() {
-                DMAWidth::Width8Bit => 1,
-                DMAWidth::Width16Bit => 2,
-                DMAWidth::Width32Bit => 4,
-            };
-        len = cmp::min(len, maxlen);
-        self.registers
-            .mr
-            .write(Mode::SIZE.val(self.width.get() as u32));
+    /// Add `units`, in units of the configured width, to `bytes_transferred`.
+    fn count_transferred(&self, units: usize) {
+        let bytes = units * self.width.get().bytes();
+        self.bytes_transferred
+            .set(self.bytes_transferred.get().wrapping_add(bytes));
+    }
 
-        self.registers.psr.set(pid);
+    /// Swap the byte order of each unit of data sent to the peripheral.
+    ///
+    /// The PDCA cannot swap bytes itself, so while this is enabled transfers
+    /// to `*_TX` peripherals swap the part of the buffer they send in place
+    /// with `swap_buffer_in_place` before the channel reads it, and the
+    /// buffer is handed back to the client swapped. This applies to
+    /// `prepare_transfer`, `prepare_reload` and `start_ring_transfer`, and
+    /// only to 16 and 32-bit widths. Data received from `*_RX` peripherals is
+    /// not touched, as it only arrives once the transfer is running; the
+    /// client can swap it with `swap_buffer_in_place` once the transfer is
+    /// done.
+    ///
+    /// Each transfer buffer is only swapped once: if a transfer is aborted or
+    /// fails, passing (part of) the same data to `prepare_transfer` or
+    /// `prepare_transfer_offset` again, e.g. to retry its tail, sends it as
+    /// already swapped. Once a transfer completes, its buffer counts as new
+    /// data the next time it is passed in.
+    pub fn set_byte_swap(&self, swap: bool) {
+        self.byte_swap.set(swap);
+    }
+
+    /// Swap the bytes of `buf`, which is about to be sent to `pid`, if
+    /// `set_byte_swap` enabled it.
+    fn swap_outgoing(&self, pid: Option<DMAPeripheral>, buf: &mut [u8]) {
+        if self.byte_swap.get() && pid.is_some_and(|pid| pid.direction() == DMADirection::Tx) {
+            swap_buffer_in_place(self.width.get(), buf);
+        }
+    }
+
+    /// Like `swap_outgoing`, but skip data that is still swapped from a
+    /// transfer that did not complete, see `swapped`.
+    fn swap_outgoing_once(&self, pid: DMAPeripheral, buf: &mut [u8]) {
+        let start = buf.as_ptr() as usize;
+        let end = start + buf.len();
+        if self
+            .swapped
+            .get()
+            .is_some_and(|(swapped_start, swapped_end)| {
+                swapped_start <= start && end <= swapped_end
+            })
+        {
+            return;
+        }
+        if self.byte_swap.get() && pid.direction() == DMADirection::Tx {
+            swap_buffer_in_place(self.width.get(), buf);
+            self.swapped.set(Some((start, end)));
+        }
+    }
+
+    /// Number of units of the configured width that fit in `buf`.
+    fn capacity(&self, buf: &[u8]) -> usize {
+        buf.len() / self.width.get().bytes()
+    }
+
+    /// Prepare a transfer of `len` units of the configured width between `pid`
+    /// and `buf`. A transfer of zero units is valid: once started it completes
+    /// without touching the hardware, and the client's `transfer_done` is
+    /// called from the kernel loop as usual.
+    ///
+    /// On failure the channel is left untouched and `buf` is returned along
+    /// with:
+    ///
+    /// - `OFF`: The channel is not enabled.
+    /// - `BUSY`: The channel still holds the buffer of another transfer.
+    /// - `SIZE`: `len` units of the configured width do not fit in `buf`.
+    pub fn prepare_transfer(
+        &self,
+        pid: DMAPeripheral,
+        buf: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        self.prepare_transfer_offset(pid, buf, 0, len)
+    }
+
+    /// Like `prepare_transfer`, but start the transfer `offset` bytes into
+    /// `buf` instead of at its start, e.g. to retry only the tail of a
+    /// transfer that failed part way through.
+    ///
+    /// The number of bytes reported by `abort_transfer` counts from `offset`.
+    /// Returns the same errors as `prepare_transfer`, with `SIZE` if the
+    /// transfer would run past the end of `buf`, and additionally `INVAL` if
+    /// `offset` is not a multiple of the width.
+    pub fn prepare_transfer_offset(
+        &self,
+        pid: DMAPeripheral,
+        buf: &'static mut [u8],
+        offset: usize,
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if !self.enabled.get() {
+            return Err((ErrorCode::OFF, buf));
+        }
+        if self.buffer.is_some() {
+            return Err((ErrorCode::BUSY, buf));
+        }
+        let width = self.width.get().bytes();
+        if offset % width != 0 {
+            return Err((ErrorCode::INVAL, buf));
+        }
+        let end = len
+            .checked_mul(width)
+            .and_then(|bytes| bytes.checked_add(offset));
+        if !end.is_some_and(|end| end <= buf.len()) {
+            return Err((ErrorCode::SIZE, buf));
+        }
+
+        debug_assert!(
+            self.width.get().is_aligned(buf),
+            "DMA buffer is not aligned to the transfer width"
+        );
+
+        self.swap_outgoing_once(pid, &mut buf[offset..offset + len * width]);
+        self.prepare_transfer_at(pid, buf, offset, len);
+        Ok(())
+    }
+
+    /// Program a transfer of `len` units starting `offset` bytes into `buf`.
+    /// The caller must have checked that it fits in `buf`.
+    fn prepare_transfer_at(
+        &self,
+        pid: DMAPeripheral,
+        buf: &'static mut [u8],
+        offset: usize,
+        len: usize,
+    ) {
+        // Make sure the PDCA sees what the CPU last wrote to `buf`. This is
+        // `Chip::data_barrier()`, which the channel cannot reach.
+        cortexm4::support::dsb();
+
+        self.registers.mr.write(self.mode());
+
+        self.set_peripheral(pid);
+
+        // With progress interrupts enabled, only the first segment is loaded
+        // now. The channel is idle, so it moves straight from the reload
+        // registers into the current ones and the second segment can be
+        // queued behind it.
+        // Transfers split into chunks are started the same way, but the next
+        // chunk is only programmed once this one is done.
+        let every = self.progress_every.get();
+        let chunk = self.chunk_size.get();
+        let first = if every != 0 && len > every {
+            every
+        } else if chunk != 0 && len > chunk {
+            chunk
+        } else {
+            len
+        };
         self.registers
             .marr
-            .write(MemoryAddressReload::MARV.val(core::ptr::from_ref::<u8>(&buf[0]) as u32));
+            .write(MemoryAddressReload::MARV.val(buf[offset..].as_ptr() as u32));
         self.registers
             .tcrr
-            .write(TransferCounter::TCV.val(len as u32));
+            .write(TransferCounter::TCV.val(first as u32));
 
-        self.registers.ier.write(Interrupt::TRC::SET);
+        self.registers
+            .ier
+            .write(Interrupt::TERR::SET + Interrupt::TRC::SET);
 
         // Store the buffer reference in the TakeCell so it can be returned to
         // the caller in `handle_interrupt`
         self.buffer.replace(buf);
+        self.len.set(len);
+        self.offset.set(offset);
+
+        self.progress_queued.set(0);
+        self.chunk_next.set(0);
+        if first < len {
+            if every != 0 {
+                self.progress_queued.set(first);
+                self.progress_last.set(first);
+                self.queue_progress_segment();
+            } else {
+                self.chunk_next.set(first);
+            }
+        }
     }
 
-    pub fn do_transfer(&self, pid: DMAPeripheral, buf: &'static mut [u8], len: usize) {
-        self.prepare_transfer(pid, buf, len);
+    /// Prepare a transfer with `prepare_transfer` and start it. Returns the
+    /// same errors as `prepare_transfer`, in which case nothing is started.
+    pub fn do_transfer(
+        &self,
+        pid: DMAPeripheral,
+        buf: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        self.prepare_transfer(pid, buf, len)?;
         self.start_transfer();
+        Ok(())
+    }
+
+    /// Configure and start a transfer on this channel with a
+    /// `TransferBuilder`, which checks the whole configuration before
+    /// touching the channel.
+    pub fn transfer(&self) -> TransferBuilder<'_> {
+        TransferBuilder {
+            channel: self,
+            peripheral: None,
+            width: None,
+            buffer: None,
+            len: None,
+            offset: 0,
+        }
+    }
+
+    /// Set the alarm used to time out transfers started with
+    /// `start_transfer_with_timeout`. The channel becomes the alarm's client,
+    /// so the alarm must not be shared with anything else, e.g. use a
+    /// dedicated virtual alarm.
+    pub fn set_timeout_alarm(&'static self, alarm: &'static DMATimeoutAlarm) {
+        alarm.set_alarm_client(self);
+        self.timeout_alarm.set(alarm);
+    }
+
+    /// Like `do_transfer`, but give up on the transfer if it has not finished
+    /// after `timeout_us` microseconds, e.g. because the peripheral stopped
+    /// responding. In that case the channel is stopped and the client's
+    /// `transfer_timeout` callback is called instead of `transfer_done`.
+    ///
+    /// Returns `NOSUPPORT` along with `buf` if no alarm was set with
+    /// `set_timeout_alarm`, and `BUSY` if the channel is in use.
+    pub fn start_transfer_with_timeout(
+        &self,
+        pid: DMAPeripheral,
+        buf: &'static mut [u8],
+        len: usize,
+        timeout_us: u32,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        let Some(alarm) = self.timeout_alarm.get() else {
+            return Err((ErrorCode::NOSUPPORT, buf));
+        };
+        if self.buffer.is_some() || self.ring.get() || self.relay_partner.is_some() {
+            return Err((ErrorCode::BUSY, buf));
+        }
+
+        self.do_transfer(pid, buf, len)?;
+        self.timeout_pending.set(true);
+        alarm.set_alarm(alarm.now(), alarm.ticks_from_us(timeout_us));
+        Ok(())
+    }
+
+    /// Deliver `transfer_done` through a deferred call instead of from
+    /// `handle_interrupt`.
+    ///
+    /// `handle_interrupt` runs from the kernel loop while it services pending
+    /// interrupts, so a client doing heavy post-transfer processing in
+    /// `transfer_done` delays servicing of every other peripheral. When
+    /// enabled, the callback instead runs once those interrupts have been
+    /// serviced. Other callbacks, e.g. `reload_needed`, are still called
+    /// right away, as they must keep the channel fed.
+    ///
+    /// Returns `NOSUPPORT` if no `DMADeferredDone` has been registered for
+    /// the channel.
+    pub fn set_deferred_callbacks(&self, enabled: bool) -> Result<(), ErrorCode> {
+        if self.deferred_done.is_none() {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+        self.defer_callbacks.set(enabled);
+        Ok(())
+    }
+
+    /// Forget a `transfer_done` callback scheduled by `handle_interrupt` that
+    /// has not been delivered yet.
+    fn cancel_deferred_done(&self) {
+        self.deferred_done.map(|deferred| deferred.cancel(self.num));
+    }
+
+    /// Disarm the timeout of the current transfer, if it has one.
+    fn cancel_timeout(&self) {
+        if self.timeout_pending.replace(false) {
+            self.timeout_alarm.map(|alarm| {
+                let _ = alarm.disarm();
+            });
+        }
+    }
+
+    /// Queue `buf` to be transferred right after the current segment
+    /// finishes, using the PDCA reload registers.
+    ///
+    /// When the PDCA switches to this segment the client's `reload_needed`
+    /// callback is called with the buffer of the segment that finished. Only
+    /// one reload can be pending at a time; if one already is, this returns
+    /// `BUSY` along with `buf`. Returns `SIZE` if `buf` is empty or `len`
+    /// units of the configured width do not fit in it.
+    pub fn prepare_reload(
+        &self,
+        buf: &'static mut [u8],
+        len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if self.reload_buffer.is_some()
+            || self.ring.get()
+            || self.progress_active()
+            || self.chunking_active()
+            || self.relay_partner.is_some()
+        {
+            return Err((ErrorCode::BUSY, buf));
+        }
+        if buf.is_empty() || len > self.capacity(buf) {
+            return Err((ErrorCode::SIZE, buf));
+        }
+        if !self.width.get().is_aligned(buf) {
+            return Err((ErrorCode::INVAL, buf));
+        }
+
+        self.swap_outgoing(
+            self.peripheral(),
+            &mut buf[..len * self.width.get().bytes()],
+        );
+        self.registers
+            .marr
+            .write(MemoryAddressReload::MARV.val(core::ptr::from_ref::<u8>(&buf[0]) as u32));
+        self.registers
+            .tcrr
+            .write(TransferCounter::TCV.val(len as u32));
+
+        self.reload_buffer.replace(buf);
+        self.reload_len.set(len);
+        self.registers.ier.write(Interrupt::RCZ::SET);
+        Ok(())
+    }
+
+    /// Start a double buffered transfer, for gapless streaming: the channel
+    /// transfers `buf_a`, then switches to `buf_b` using the reload registers.
+    /// While one buffer is transferred the client refills the other, which it
+    /// gets back through the `buffer_ready` callback, and queues it again with
+    /// `prepare_reload`. If the client does not do so in time the transfer
+    /// stops and `buffer_underrun` is called.
+    ///
+    /// The transfer runs until `abort_transfer` is called, which returns the
+    /// current buffer; `abort_reload` returns the queued one. Returns `BUSY`
+    /// along with both buffers if the channel is in use or has progress
+    /// interrupts or chunking enabled, `SIZE` if either buffer is empty, and `INVAL` if
+    /// either is not aligned to the configured width.
+    pub fn start_pingpong(
+        &self,
+        pid: DMAPeripheral,
+        buf_a: &'static mut [u8],
+        buf_b: &'static mut [u8],
+    ) -> Result<(), (ErrorCode, &'static mut [u8], &'static mut [u8])> {
+        if self.buffer.is_some()
+            || self.reload_buffer.is_some()
+            || self.ring.get()
+            || self.relay_partner.is_some()
+            || self.progress_every.get() != 0
+            || self.chunk_size.get() != 0
+        {
+            return Err((ErrorCode::BUSY, buf_a, buf_b));
+        }
+        if self.capacity(buf_a) == 0 || self.capacity(buf_b) == 0 {
+            return Err((ErrorCode::SIZE, buf_a, buf_b));
+        }
+        if !self.width.get().is_aligned(buf_a) || !self.width.get().is_aligned(buf_b) {
+            return Err((ErrorCode::INVAL, buf_a, buf_b));
+        }
+
+        let len = self.capacity(buf_a);
+        if let Err((err, buf_a)) = self.prepare_transfer(pid, buf_a, len) {
+            return Err((err, buf_a, buf_b));
+        }
+        let len = self.capacity(buf_b);
+        // This cannot fail, the checks above cover everything
+        // `prepare_reload` checks.
+        let _ = self.prepare_reload(buf_b, len);
+        self.pingpong.set(true);
+        self.pingpong_which.set(0);
+        self.start_transfer();
+        Ok(())
+    }
+
+    /// Start transferring `buf` in ring mode: once the end of `buf` is
+    /// reached the channel starts over at its beginning, indefinitely. This is
+    /// useful for continuous streams such as DAC output or ADC sampling.
+    ///
+    /// The client's `ring_wrapped` callback is called on each wrap. A ring
+    /// transfer can only be stopped with `stop_ring_transfer`, which also
+    /// returns the buffer; `abort_transfer` has no effect on it.
+    pub fn start_ring_transfer(
+        &self,
+        pid: DMAPeripheral,
+        buf: &'static mut [u8],
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if self.buffer.is_some() || self.ring.get() || self.relay_partner.is_some() {
+            return Err((ErrorCode::BUSY, buf));
+        }
+        let len = self.capacity(buf);
+        if len == 0 {
+            return Err((ErrorCode::SIZE, buf));
+        }
+        if !self.width.get().is_aligned(buf) {
+            return Err((ErrorCode::INVAL, buf));
+        }
+
+        self.swap_outgoing(Some(pid), &mut buf[..len * self.width.get().bytes()]);
+        // The PDCA's own ring mode never clears the reload registers, so RCZ
+        // never fires and wraps would go unnoticed. Instead, the buffer is
+        // queued in the reload registers again each time the channel moves on
+        // to it, which raises RCZ once per lap.
+        self.registers.mr.write(self.mode());
+        self.set_peripheral(pid);
+
+        self.buffer.replace(buf);
+        self.len.set(len);
+        self.ring.set(true);
+
+        // The channel is idle, so the first copy moves straight into the
+        // current registers and the second one is queued behind it.
+        self.queue_ring_lap();
+        self.queue_ring_lap();
+
+        self.registers
+            .ier
+            .write(Interrupt::TERR::SET + Interrupt::RCZ::SET);
+        self.start_transfer();
+        Ok(())
+    }
+
+    /// Stop a ring transfer started with `start_ring_transfer` and return its
+    /// buffer. Returns `None` if no ring transfer is running.
+    pub fn stop_ring_transfer(&self) -> Option<&'static mut [u8]> {
+        if !self.ring.get() {
+            return None;
+        }
+
+        self.registers.cr.write(Control::TDIS::SET);
+        self.registers
+            .idr
+            .write(Interrupt::TERR::SET + Interrupt::TRC::SET + Interrupt::RCZ::SET);
+        self.registers.mr.write(self.mode() + Mode::RING::Disable);
+        self.registers.tcrr.write(TransferCounter::TCV.val(0));
+        self.registers.tcr.write(TransferCounter::TCV.val(0));
+
+        self.ring.set(false);
+        self.len.set(0);
+        self.buffer.take()
     }
 
     /// Aborts any current transactions and returns the buffer used in the
-    /// transaction.
-    pub fn abort_transfer(&self) -> Option<&'static mut [u8]> {
+    /// transaction along with the number of bytes that were transferred before
+    /// the abort. A pending reload is cancelled as well, its buffer can be
+    /// retrieved with `abort_reload`.
+    ///
+    /// This does not stop ring transfers and returns `None` while one is
+    /// running, use `stop_ring_transfer` instead.
+    ///
+    /// Aborting either channel of a relay started with `relay` stops both
+    /// channels and returns the relay buffer, along with the number of bytes
+    /// the channel holding it transferred in its current pass.
+    pub fn abort_transfer(&self) -> Option<(&'static mut [u8], usize)> {
+        if self.ring.get() {
+            return None;
+        }
+
+        if let Some(partner) = self.relay_partner.take() {
+            partner.relay_partner.clear();
+            return self.abort_current().or_else(|| partner.abort_current());
+        }
+
+        self.abort_current()
+    }
+
+    /// Copy `len` bytes from `src` to `dst` without using the CPU.
+    ///
+    /// The PDCA can only transfer between memory and a peripheral: every
+    /// channel is tied to a peripheral through `psr`, and there is no
+    /// memory-to-memory mode. This therefore always returns `NOSUPPORT` along
+    /// with both buffers, so that generic code can fall back to copying with
+    /// the CPU.
+    pub fn memcpy(
+        &self,
+        dst: &'static mut [u8],
+        src: &'static [u8],
+        _len: usize,
+    ) -> Result<(), (ErrorCode, &'static mut [u8], &'static [u8])> {
+        Err((ErrorCode::NOSUPPORT, dst, src))
+    }
+
+    /// Copy data from peripheral `src` to peripheral `dst`, using this channel
+    /// to receive from `src` into `buf` and `tx` to send `buf` to `dst`.
+    ///
+    /// The PDCA cannot transfer directly between two peripherals, so the
+    /// channels take turns: once this channel has filled `buf` it is passed to
+    /// `tx`, and once `tx` has sent it this channel fills it again. This
+    /// continues until `abort_transfer` is called on either channel, which
+    /// returns `buf`. The clients of the channels are not called while
+    /// relaying.
+    ///
+    /// Both channels must be enabled and idle. Returns `BUSY` along with `buf`
+    /// if either is in use, `OFF` if either is not enabled, `INVAL` if `tx`
+    /// is this channel, and `SIZE` if `buf` is too short for a single
+    /// transfer.
+    pub fn relay(
+        &'static self,
+        tx: &'static DMAChannel,
+        src: DMAPeripheral,
+        dst: DMAPeripheral,
+        buf: &'static mut [u8],
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        if core::ptr::eq(self, tx) {
+            return Err((ErrorCode::INVAL, buf));
+        }
+        let busy = |channel: &DMAChannel| {
+            channel.buffer.is_some()
+                || channel.reload_buffer.is_some()
+                || channel.ring.get()
+                || channel.relay_partner.is_some()
+        };
+        if busy(self) || busy(tx) {
+            return Err((ErrorCode::BUSY, buf));
+        }
+        if !self.enabled.get() || !tx.enabled.get() {
+            return Err((ErrorCode::OFF, buf));
+        }
+        if self.capacity(buf) == 0 || tx.capacity(buf) == 0 {
+            return Err((ErrorCode::SIZE, buf));
+        }
+
+        tx.set_peripheral(dst);
+        self.relay_partner.set(tx);
+        tx.relay_partner.set(self);
+
+        let len = self.capacity(buf);
+        self.do_transfer(src, buf, len)
+    }
+
+    /// Abort the transfer of this channel only, see `abort_transfer`.
+    fn abort_current(&self) -> Option<(&'static mut [u8], usize)> {
+        self.cancel_timeout();
+        self.cancel_deferred_done();
+        self.pingpong.set(false);
         self.registers
             .idr
             .write(Interrupt::TERR::SET + Interrupt::TRC::SET + Interrupt::RCZ::SET);
 
-        // Reset counter
+        // The transfer counter counts down the remaining transfers, each of
+        // which moves `width` bytes. A transfer split up for progress
+        // interrupts also has a queued segment that was not started yet.
+        let transferred = if self.progress_active() {
+            let remaining =
+                self.transfer_counter() + self.registers.tcrr.read(TransferCounter::TCV) as usize;
+            self.progress_queued.get().saturating_sub(remaining)
+        } else if self.chunking_active() {
+            self.chunk_next
+                .get()
+                .saturating_sub(self.transfer_counter())
+        } else {
+            self.len.get().saturating_sub(self.transfer_counter())
+        };
+        let transferred_bytes = transferred * self.width.get().bytes();
+        self.len.set(0);
+        self.progress_queued.set(0);
+        self.chunk_next.set(0);
+
+        // Reset counters. The reload counter must be cleared first, otherwise
+        // clearing the current counter would start the reload segment.
+        self.registers.tcrr.write(TransferCounter::TCV.val(0));
         self.registers.tcr.write(TransferCounter::TCV.val(0));
 
-        self.buffer.take()
+        self.buffer.take().map(|buf| (buf, transferred_bytes))
+    }
+
+    /// Returns the buffer of a reload segment that was queued with
+    /// `prepare_reload` but not started before the transfer was aborted.
+    pub fn abort_reload(&self) -> Option<&'static mut [u8]> {
+        self.registers.idr.write(Interrupt::RCZ::SET);
+        self.registers.tcrr.write(TransferCounter::TCV.val(0));
+
+        self.reload_buffer.take()
+    }
+
+    /// Return the channel to the state it was in after `new`, e.g. to recover
+    /// after `transfer_error`: stop the channel, disable its interrupts, clear
+    /// any error condition, zero the transfer counters, and forget the
+    /// peripheral and any ring transfer, relay, or pending reload.
+    ///
+    /// Any buffers still held by the channel are dropped, so retrieve them
+    /// with `abort_transfer` and `abort_reload` first if they are needed
+    /// again. This does not change the configured width, trigger, progress
+    /// interval, chunk size or client, nor whether the channel is enabled.
+    pub fn reset(&self) {
+        self.cancel_timeout();
+        self.cancel_deferred_done();
+        self.registers.cr.write(Control::TDIS::SET);
+        self.registers
+            .idr
+            .write(Interrupt::TERR::SET + Interrupt::TRC::SET + Interrupt::RCZ::SET);
+        self.registers.cr.write(Control::ECLR::SET);
+        self.registers.mr.write(self.mode() + Mode::RING::Disable);
+        self.registers.tcrr.write(TransferCounter::TCV.val(0));
+        self.registers.tcr.write(TransferCounter::TCV.val(0));
+
+        if let Some(partner) = self.relay_partner.take() {
+            partner.relay_partner.clear();
+        }
+        self.buffer.take();
+        self.reload_buffer.take();
+        self.len.set(0);
+        self.offset.set(0);
+        self.reload_len.set(0);
+        self.ring.set(false);
+        self.pingpong.set(false);
+        self.progress_queued.set(0);
+        self.progress_last.set(0);
+        self.chunk_next.set(0);
+        self.peripheral_set.set(false);
     }
 
     pub fn transfer_counter(&self) -> usize {
         self.registers.tcr.read(TransferCounter::TCV) as usize
     }
+
+    /// The memory address the channel will access next. This advances as the
+    /// transfer progresses, so comparing it with the start of the buffer
+    /// shows how far the transfer has got, e.g. to diagnose a stall. It is
+    /// not meaningful while the channel has no transfer.
+    pub fn current_address(&self) -> u32 {
+        self.registers.mar.read(MemoryAddress::MADDR)
+    }
+
+    /// Call `f` with the buffer of the current transfer, without taking it out
+    /// of the channel, e.g. to inspect the start of a streaming transfer while
+    /// debugging. Returns `None` if the channel holds no buffer.
+    ///
+    /// For a transfer from memory to a peripheral (`*_TX`) the PDCA only reads
+    /// the buffer, so it is safe to inspect. For a transfer from a peripheral
+    /// to memory (`*_RX`) the PDCA may write the buffer while `f` reads it, so
+    /// `f` can observe partially transferred data and must not rely on its
+    /// contents staying the same. Bytes outside of the transfer's range are
+    /// never written.
+    pub fn peek_buffer<R>(&self, f: impl FnOnce(&[u8]) -> R) -> Option<R> {
+        self.buffer.map(|buf| f(buf))
+    }
+}
+
+impl time::AlarmClient for DMAChannel {
+    fn alarm(&self) {
+        if !self.timeout_pending.replace(false) {
+            return;
+        }
+
+        // Stop the channel but leave the buffer and transfer counter alone, so
+        // that `abort_transfer` can return them to the client.
+        self.registers.cr.write(Control::TDIS::SET);
+        self.registers
+            .idr
+            .write(Interrupt::TERR::SET + Interrupt::TRC::SET + Interrupt::RCZ::SET);
+
+        let channel = self.registers.psr.get();
+        self.client.map(|client| client.transfer_timeout(channel));
+    }
+}
+
+/// How urgently a peripheral needs its DMA transfers serviced, see
+/// `DMAChannelAllocator::allocate`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DMAPriority {
+    /// Latency-critical peripherals, such as audio.
+    High,
+    /// Background peripherals, such as flash logging.
+    Low,
+}
+
+/// Hands out DMA channels by priority and tracks which are in use.
+///
+/// When several channels have pending requests the PDCA services the lowest
+/// numbered one first, so high priority allocations get the lowest numbered
+/// free channel and low priority allocations the highest numbered one.
+pub struct DMAChannelAllocator {
+    channels: &'static [DMAChannel],
+    /// Bit `n` is set if channel `n` is in use.
+    in_use: Cell<u16>,
+}
+
+impl DMAChannelAllocator {
+    /// Create an allocator for `channels`, e.g. `dma_channels` of
+    /// `Sam4lDefaultPeripherals`, with all of them free.
+    pub const fn new(channels: &'static [DMAChannel]) -> DMAChannelAllocator {
+        DMAChannelAllocator {
+            channels,
+            in_use: Cell::new(0),
+        }
+    }
+
+    fn is_free(&self, channel: &DMAChannel) -> bool {
+        self.in_use.get() & (1 << channel.num as u16) == 0
+    }
+
+    fn mark(&self, channel: &DMAChannel, in_use: bool) {
+        let bit = 1 << channel.num as u16;
+        let mask = self.in_use.get();
+        self.in_use
+            .set(if in_use { mask | bit } else { mask & !bit });
+    }
+
+    /// Allocate a free channel for a peripheral with the given priority.
+    /// Returns `None` if all channels are in use.
+    pub fn allocate(&self, priority: DMAPriority) -> Option<&'static DMAChannel> {
+        let channels = self.channels;
+        let channel = match priority {
+            DMAPriority::High => channels.iter().find(|channel| self.is_free(channel)),
+            DMAPriority::Low => channels.iter().rev().find(|channel| self.is_free(channel)),
+        }?;
+        self.mark(channel, true);
+        Some(channel)
+    }
+
+    /// Allocate the channel with number `num`, for peripherals whose channel
+    /// is fixed. Returns `None` if it is in use or not managed by this
+    /// allocator.
+    pub fn claim(&self, num: DMAChannelNum) -> Option<&'static DMAChannel> {
+        let channels = self.channels;
+        let channel = channels
+            .iter()
+            .find(|channel| channel.num as usize == num as usize)?;
+        if !self.is_free(channel) {
+            return None;
+        }
+        self.mark(channel, true);
+        Some(channel)
+    }
+
+    /// Return `channel` to the allocator so it can be allocated again. The
+    /// channel should be idle.
+    pub fn release(&self, channel: &'static DMAChannel) {
+        self.mark(channel, false);
+    }
+}
+
+/// Services completions on a set of DMA channels in round-robin order.
+///
+/// Each PDCA channel has its own NVIC line, and the chip services pending
+/// lines lowest number first. When several channels complete at once under
+/// load, e.g. an audio and a logging channel, this starves the higher
+/// numbered ones. A board can instead route all of the channels' interrupts
+/// to `service_all`, which starts with the channel after the one it serviced
+/// last.
+pub struct DMA {
+    channels: &'static [DMAChannel],
+    /// Index in `channels` of the channel serviced last.
+    last_serviced: Cell<usize>,
+}
+
+impl DMA {
+    /// Create a dispatcher for `channels`, e.g. `dma_channels` of
+    /// `Sam4lDefaultPeripherals`.
+    pub const fn new(channels: &'static [DMAChannel]) -> DMA {
+        DMA {
+            channels,
+            last_serviced: Cell::new(0),
+        }
+    }
+
+    /// Call `handle_interrupt` on every channel with a pending interrupt
+    /// condition, in round-robin order starting after the channel serviced
+    /// last. The NVIC lines of serviced channels are cleared, so their
+    /// interrupts are not delivered again for the same event, and re-enabled,
+    /// as the generic interrupt handler disabled them when they fired.
+    ///
+    /// Channels whose interrupt is set in `masked_interrupts`, one bit per
+    /// interrupt number, are left alone so that they stay masked.
+    pub fn service_all(&self, masked_interrupts: u128) {
+        let count = self.channels.len();
+        let start = self.last_serviced.get() + 1;
+        for offset in 0..count {
+            let index = (start + offset) % count;
+            let channel = &self.channels[index];
+            let masked = masked_interrupts & (1 << channel.nvic_number()) != 0;
+            if !masked && channel.interrupt_pending() {
+                channel.handle_interrupt();
+                channel.nvic().clear_pending();
+                channel.nvic().enable();
+                self.last_serviced.set(index);
+            }
+        }
+    }
+}
+
+/// Deferred call shared by DMA channels to deliver `transfer_done` outside of
+/// `handle_interrupt`, see `DMAChannel::set_deferred_callbacks`.
+///
+/// A board that wants deferred callbacks creates one for all of its channels
+/// and registers it, which makes it available to every channel:
+///
+/// ```rust,ignore
+/// let dma_deferred = static_init!(
+///     sam4l::dma::DMADeferredDone,
+///     sam4l::dma::DMADeferredDone::new(&peripherals.dma_channels)
+/// );
+/// kernel::deferred_call::DeferredCallClient::register(dma_deferred);
+/// ```
+pub struct DMADeferredDone {
+    deferred_call: DeferredCall,
+    channels: &'static [DMAChannel],
+    /// Bit `n` is set if channel `n` has a `transfer_done` to deliver.
+    pending: Cell<u16>,
+}
+
+impl DMADeferredDone {
+    pub fn new(channels: &'static [DMAChannel]) -> DMADeferredDone {
+        DMADeferredDone {
+            deferred_call: DeferredCall::new(),
+            channels,
+            pending: Cell::new(0),
+        }
+    }
+
+    fn schedule(&self, channel: &DMAChannel) {
+        self.pending
+            .set(self.pending.get() | (1 << channel.num as u16));
+        self.deferred_call.set();
+    }
+
+    fn cancel(&self, num: DMAChannelNum) {
+        self.pending.set(self.pending.get() & !(1 << num as u16));
+    }
+}
+
+impl DeferredCallClient for DMADeferredDone {
+    fn handle_deferred_call(&self) {
+        for channel in self.channels {
+            let bit = 1 << channel.num as u16;
+            if self.pending.get() & bit != 0 {
+                self.pending.set(self.pending.get() & !bit);
+                let pid = channel.registers.psr.get();
+                channel.client.map(|client| client.transfer_done(pid));
+            }
+        }
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+        for channel in self.channels {
+            channel.deferred_done.set(self);
+        }
+    }
+}
+
+/// Checked configuration of a single transfer, created by
+/// `DMAChannel::transfer`.
+///
+/// This wraps `set_width_checked`, `prepare_transfer_offset` and
+/// `start_transfer`, so the steps cannot happen out of order or with a
+/// misaligned buffer:
+///
+/// ```rust,ignore
+/// channel
+///     .transfer()
+///     .peripheral(DMAPeripheral::USART0_TX)
+///     .width(DMAWidth::Width8Bit)
+///     .buffer(buf)
+///     .len(n)
+///     .start()?;
+/// ```
+///
+/// The peripheral and buffer are required. Without `width` the channel's
+/// current width is used, without `offset` the transfer starts at the
+/// beginning of the buffer, and without `len` it runs to the end of it.
+pub struct TransferBuilder<'c> {
+    channel: &'c DMAChannel,
+    peripheral: Option<DMAPeripheral>,
+    width: Option<DMAWidth>,
+    buffer: Option<&'static mut [u8]>,
+    len: Option<usize>,
+    offset: usize,
+}
+
+impl TransferBuilder<'_> {
+    /// The peripheral to transfer to or from.
+    pub fn peripheral(mut self, peripheral: DMAPeripheral) -> Self {
+        self.peripheral = Some(peripheral);
+        self
+    }
+
+    /// The width of each unit transferred. This remains the channel's width
+    /// after the transfer.
+    pub fn width(mut self, width: DMAWidth) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// The memory side of the transfer.
+    pub fn buffer(mut self, buffer: &'static mut [u8]) -> Self {
+        self.buffer = Some(buffer);
+        self
+    }
+
+    /// The number of units to transfer.
+    pub fn len(mut self, len: usize) -> Self {
+        self.len = Some(len);
+        self
+    }
+
+    /// The number of bytes into the buffer at which the transfer starts.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Check the configuration and start the transfer. On error the channel
+    /// is left unchanged and the buffer, if one was given, is returned:
+    ///
+    /// - `INVAL`: The peripheral or buffer is missing, or the buffer or
+    ///   offset is not aligned to the width.
+    /// - `SIZE`: The transfer would run past the end of the buffer.
+    /// - `BUSY`: The channel still holds the buffer of another transfer.
+    /// - `OFF`: The channel is not enabled.
+    pub fn start(self) -> Result<(), (ErrorCode, Option<&'static mut [u8]>)> {
+        let channel = self.channel;
+        let Some(buf) = self.buffer else {
+            return Err((ErrorCode::INVAL, None));
+        };
+        let Some(pid) = self.peripheral else {
+            return Err((ErrorCode::INVAL, Some(buf)));
+        };
+        if !channel.enabled.get() {
+            return Err((ErrorCode::OFF, Some(buf)));
+        }
+        if channel.buffer.is_some() {
+            return Err((ErrorCode::BUSY, Some(buf)));
+        }
+
+        let width = self.width.unwrap_or(channel.width.get());
+        let bytes = width.bytes();
+        if !width.is_aligned(buf) || self.offset % bytes != 0 {
+            return Err((ErrorCode::INVAL, Some(buf)));
+        }
+        let Some(available) = buf.len().checked_sub(self.offset) else {
+            return Err((ErrorCode::SIZE, Some(buf)));
+        };
+        let len = self.len.unwrap_or(available / bytes);
+        if len.checked_mul(bytes).is_none_or(|size| size > available) {
+            return Err((ErrorCode::SIZE, Some(buf)));
+        }
+
+        // Everything `prepare_transfer_offset` checks has been checked above,
+        // but should it fail anyway, restore the width.
+        let previous_width = channel.width.replace(width);
+        channel
+            .prepare_transfer_offset(pid, buf, self.offset, len)
+            .map_err(|(err, buf)| {
+                channel.width.set(previous_width);
+                (err, Some(buf))
+            })?;
+        channel.start_transfer();
+        Ok(())
+    }
 }
@@ -25,6 +25,7 @@ use kernel::utilities::registers::{
     register_bitfields, FieldValue, ReadOnly, ReadWrite, WriteOnly,
 };
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 
 // Listing of all registers related to the TWIM peripheral.
 // Section 27.9 of the datasheet
@@ -631,6 +632,14 @@ const fn create_twims_clocks(
     (TWIMClock { master, slave }, TWISClock { master, slave })
 }
 
+/// Convert an error from preparing a DMA transfer to the closest I2C error.
+fn dma_error(err: ErrorCode) -> hil::i2c::Error {
+    match err {
+        ErrorCode::BUSY => hil::i2c::Error::Busy,
+        _ => hil::i2c::Error::NotSupported,
+    }
+}
+
 // Need to implement the `new` function on the I2C device as a constructor.
 // This gets called from the device tree.
 impl<'a> I2CHw<'a> {
@@ -806,7 +815,7 @@ impl<'a> I2CHw<'a> {
                 err.map(|err| {
                     self.master_client.get().map(|client| {
                         let buf = self.dma.and_then(|dma| {
-                            let b = dma.abort_transfer();
+                            let b = dma.abort_transfer().map(|(buf, _)| buf);
                             self.dma.set(dma);
                             b
                         });
@@ -845,7 +854,7 @@ impl<'a> I2CHw<'a> {
                     err.map(|err| {
                         self.master_client.get().map(|client| {
                             let buf = self.dma.and_then(|dma| {
-                                let b = dma.abort_transfer();
+                                let b = dma.abort_transfer().map(|(buf, _)| buf);
                                 self.dma.set(dma);
                                 b
                             });
@@ -868,9 +877,26 @@ impl<'a> I2CHw<'a> {
                         );
                     }
                     self.dma.map(|dma| {
-                        let buf = dma.abort_transfer().unwrap();
-                        dma.prepare_transfer(dma_periph, buf, len);
-                        dma.start_transfer();
+                        let (buf, _) = dma.abort_transfer().unwrap();
+                        match dma.prepare_transfer(dma_periph, buf, len) {
+                            Ok(()) => dma.start_transfer(),
+                            Err((_, buf)) => {
+                                // `write_read` checked that the read fits in
+                                // the buffer, so this should not happen. If it
+                                // does, give up on the read.
+                                let twim = &TWIMRegisterManager::new(self);
+                                twim.registers.cmdr.set(0);
+                                twim.registers.ncmdr.set(0);
+                                self.disable_interrupts(twim);
+                                twim.registers.cr.write(Control::MEN::SET);
+                                twim.registers.cr.write(Control::SWRST::SET);
+                                twim.registers.cr.write(Control::MDIS::SET);
+                                self.master_client.get().map(|client| {
+                                    client
+                                        .command_complete(buf, Err(hil::i2c::Error::NotSupported));
+                                });
+                            }
+                        }
                     });
                 }
             }
@@ -943,18 +969,16 @@ impl<'a> I2CHw<'a> {
         len: usize,
     ) -> Result<(), (hil::i2c::Error, &'static mut [u8])> {
         let twim = &TWIMRegisterManager::new(self);
-        if self.dma.is_some() {
-            self.dma.map(move |dma| {
-                dma.enable();
-                dma.prepare_transfer(self.dma_pids.1, data, len);
-                self.setup_transfer(twim, chip, flags, Command::READ::Transmit, len);
-                self.master_enable(twim);
-                dma.start_transfer();
-            });
-            Ok(())
-        } else {
-            Err((hil::i2c::Error::NotSupported, data))
-        }
+        let Some(dma) = self.dma.get() else {
+            return Err((hil::i2c::Error::NotSupported, data));
+        };
+        dma.enable();
+        dma.prepare_transfer(self.dma_pids.1, data, len)
+            .map_err(|(err, data)| (dma_error(err), data))?;
+        self.setup_transfer(twim, chip, flags, Command::READ::Transmit, len);
+        self.master_enable(twim);
+        dma.start_transfer();
+        Ok(())
     }
 
     fn read(
@@ -965,18 +989,16 @@ impl<'a> I2CHw<'a> {
         len: usize,
     ) -> Result<(), (hil::i2c::Error, &'static mut [u8])> {
         let twim = &TWIMRegisterManager::new(self);
-        if self.dma.is_some() {
-            self.dma.map(move |dma| {
-                dma.enable();
-                dma.prepare_transfer(self.dma_pids.0, data, len);
-                self.setup_transfer(twim, chip, flags, Command::READ::Receive, len);
-                self.master_enable(twim);
-                dma.start_transfer();
-            });
-            Ok(())
-        } else {
-            Err((hil::i2c::Error::NotSupported, data))
-        }
+        let Some(dma) = self.dma.get() else {
+            return Err((hil::i2c::Error::NotSupported, data));
+        };
+        dma.enable();
+        dma.prepare_transfer(self.dma_pids.0, data, len)
+            .map_err(|(err, data)| (dma_error(err), data))?;
+        self.setup_transfer(twim, chip, flags, Command::READ::Receive, len);
+        self.master_enable(twim);
+        dma.start_transfer();
+        Ok(())
     }
 
     fn write_read(
@@ -987,31 +1009,34 @@ impl<'a> I2CHw<'a> {
         read_len: usize,
     ) -> Result<(), (hil::i2c::Error, &'static mut [u8])> {
         let twim = &TWIMRegisterManager::new(self);
-        if self.dma.is_some() {
-            self.dma.map(move |dma| {
-                dma.enable();
-                dma.prepare_transfer(self.dma_pids.1, data, split);
-                self.setup_transfer(
-                    twim,
-                    chip,
-                    Command::START::StartCondition,
-                    Command::READ::Transmit,
-                    split,
-                );
-                self.setup_nextfer(
-                    twim,
-                    chip,
-                    Command::START::StartCondition + Command::STOP::SendStop,
-                    Command::READ::Receive,
-                    read_len,
-                );
-                self.on_deck.set(Some((self.dma_pids.0, read_len)));
-                dma.start_transfer();
-            });
-            Ok(())
-        } else {
-            Err((hil::i2c::Error::NotSupported, data))
+        let Some(dma) = self.dma.get() else {
+            return Err((hil::i2c::Error::NotSupported, data));
+        };
+        // The read reuses the buffer once the write is done, so check now
+        // that it fits rather than failing part way through.
+        if read_len > data.len() {
+            return Err((hil::i2c::Error::NotSupported, data));
         }
+        dma.enable();
+        dma.prepare_transfer(self.dma_pids.1, data, split)
+            .map_err(|(err, data)| (dma_error(err), data))?;
+        self.setup_transfer(
+            twim,
+            chip,
+            Command::START::StartCondition,
+            Command::READ::Transmit,
+            split,
+        );
+        self.setup_nextfer(
+            twim,
+            chip,
+            Command::START::StartCondition + Command::STOP::SendStop,
+            Command::READ::Receive,
+            read_len,
+        );
+        self.on_deck.set(Some((self.dma_pids.0, read_len)));
+        dma.start_transfer();
+        Ok(())
     }
 
     fn disable_interrupts(&self, twim: &TWIMRegisterManager) {
@@ -74,7 +74,11 @@ pub static BASE_VECTORS: [unsafe extern "C" fn(); 16] = [
 )]
 // used Ensures that the symbol is kept until the final binary
 #[cfg_attr(all(target_arch = "arm", target_os = "none"), used)]
-pub static IRQS: [unsafe extern "C" fn(); 80] = [CortexM4::GENERIC_ISR; 80];
+pub static IRQS: [unsafe extern "C" fn(); 80] = {
+    let mut irqs = [CortexM4::GENERIC_ISR; 80];
+    irqs[nvic::WDT as usize] = wdt::warning_handler;
+    irqs
+};
 
 pub unsafe fn init() {
     cortexm4::nvic::disable_all();
@@ -10,13 +10,14 @@ use crate::flashcalw;
 use crate::gpio;
 use crate::scif;
 use core::cell::Cell;
-use core::sync::atomic::Ordering;
-use kernel::platform::chip::ClockInterface;
+use core::sync::atomic::{AtomicU32, Ordering};
+use kernel::platform::chip::{ClockConfig, ClockInterface, ResetReason};
 use kernel::utilities::registers::interfaces::{Readable, Writeable};
 use kernel::utilities::registers::{
     register_bitfields, FieldValue, ReadOnly, ReadWrite, WriteOnly,
 };
 use kernel::utilities::StaticRef;
+use kernel::ErrorCode;
 
 /// §10.7 PM::UserInterface from SAM4L Datasheet.
 #[repr(C)]
@@ -546,6 +547,65 @@ pub enum SystemClockSource {
     RC80M,
 }
 
+impl SystemClockSource {
+    /// Encode the clock source as a single word of a `ClockConfig`: the
+    /// variant in the low byte, followed by the frequency and startup mode
+    /// of the oscillator where the variant has them.
+    fn to_word(self) -> u32 {
+        let (variant, frequency, startup) = match self {
+            SystemClockSource::RcsysAt115kHz => (0, 0, 0),
+            SystemClockSource::RC1M => (1, 0, 0),
+            SystemClockSource::RCFAST { frequency } => (2, frequency as u32, 0),
+            SystemClockSource::ExternalOscillator {
+                frequency,
+                startup_mode,
+            } => (3, frequency as u32, startup_mode as u32),
+            SystemClockSource::PllExternalOscillatorAt48MHz {
+                frequency,
+                startup_mode,
+            } => (4, frequency as u32, startup_mode as u32),
+            SystemClockSource::DfllRc32kAt48MHz => (5, 0, 0),
+            SystemClockSource::RC80M => (6, 0, 0),
+        };
+        variant | (frequency << 8) | (startup << 16)
+    }
+
+    /// Decode a word written by `to_word`.
+    fn from_word(word: u32) -> Option<SystemClockSource> {
+        let oscillator = match (word >> 8) & 0xff {
+            0 => Some(OscillatorFrequency::Frequency16MHz),
+            _ => None,
+        };
+        let rcfast = match (word >> 8) & 0xff {
+            0 => Some(RcfastFrequency::Frequency4MHz),
+            1 => Some(RcfastFrequency::Frequency8MHz),
+            2 => Some(RcfastFrequency::Frequency12MHz),
+            _ => None,
+        };
+        let startup_mode = match (word >> 16) & 0xff {
+            0 => Some(OscillatorStartup::FastStart),
+            1 => Some(OscillatorStartup::SlowStart),
+            _ => None,
+        };
+        match word & 0xff {
+            0 => Some(SystemClockSource::RcsysAt115kHz),
+            1 => Some(SystemClockSource::RC1M),
+            2 => rcfast.map(|frequency| SystemClockSource::RCFAST { frequency }),
+            3 => Some(SystemClockSource::ExternalOscillator {
+                frequency: oscillator?,
+                startup_mode: startup_mode?,
+            }),
+            4 => Some(SystemClockSource::PllExternalOscillatorAt48MHz {
+                frequency: oscillator?,
+                startup_mode: startup_mode?,
+            }),
+            5 => Some(SystemClockSource::DfllRc32kAt48MHz),
+            6 => Some(SystemClockSource::RC80M),
+            _ => None,
+        }
+    }
+}
+
 pub enum ClockMask {
     RCSYS = 0x01,
     RC1M = 0x02,
@@ -562,9 +622,17 @@ const PBB_MASK_OFFSET: u32 = 0x2C;
 const PBC_MASK_OFFSET: u32 = 0x30;
 const PBD_MASK_OFFSET: u32 = 0x34;
 
+/// Marks a `ClockConfig` as captured by `PowerManager::save_clocks`.
+const CLOCK_CONFIG_MAGIC: u32 = 0x5344_434b;
+
 const PM_BASE: usize = 0x400E0000;
 const PM_REGS: StaticRef<PmRegisters> = unsafe { StaticRef::new(PM_BASE as *const PmRegisters) };
 
+/// Frequency of the main clock, before the CPU and peripheral bus dividers.
+/// `Clock::frequency()` has no access to the `PowerManager`, so
+/// `change_system_clock` keeps this up to date. The chip starts on RCSYS.
+static MAIN_CLOCK_HZ: AtomicU32 = AtomicU32::new(115200);
+
 /// Contains state for the power management peripheral. This includes the
 /// configurations for various system clocks and the final frequency that the
 /// system is running at.
@@ -706,6 +774,7 @@ impl PowerManager {
         }
 
         self.system_clock_source.set(clock_source);
+        MAIN_CLOCK_HZ.store(self.main_clock_frequency(), Ordering::Relaxed);
     }
 
     // Disables the clock passed in as clock_source
@@ -976,6 +1045,66 @@ impl PowerManager {
             .set(clock_mask | ClockMask::RC1M as u32);
     }
 
+    /// Capture the system clock source and the clock masks of the HSB, PBA,
+    /// PBB, PBC and PBD buses, i.e. which peripheral clocks are enabled. The
+    /// bus dividers are not captured separately, as they follow from the
+    /// system clock source.
+    ///
+    /// Word 0 of the snapshot holds the system clock source, words 1 to 5
+    /// the bus clock masks, and word 6 `CLOCK_CONFIG_MAGIC`.
+    pub fn save_clocks(&self) -> ClockConfig {
+        let mut config = ClockConfig::default();
+        config.words[0] = self.system_clock_source.get().to_word();
+        config.words[1] = PM_REGS.hsbmask.get();
+        config.words[2] = PM_REGS.pbamask.get();
+        config.words[3] = PM_REGS.pbbmask.get();
+        config.words[4] = PM_REGS.pbcmask.get();
+        config.words[5] = PM_REGS.pbdmask.get();
+        config.words[6] = CLOCK_CONFIG_MAGIC;
+        config
+    }
+
+    /// Switch back to the system clock source and peripheral clocks captured
+    /// by `save_clocks`. The system clock is changed first, with
+    /// `change_system_clock`, so the bus clocks are only restored once the
+    /// flash wait states match the new frequency.
+    ///
+    /// Returns `INVAL` if `config` was not captured by `save_clocks`.
+    pub unsafe fn restore_clocks(
+        &self,
+        config: &ClockConfig,
+        flash_controller: &flashcalw::FLASHCALW,
+    ) -> Result<(), ErrorCode> {
+        if config.words[6] != CLOCK_CONFIG_MAGIC {
+            return Err(ErrorCode::INVAL);
+        }
+        let clock_source = SystemClockSource::from_word(config.words[0]).ok_or(ErrorCode::INVAL)?;
+
+        self.change_system_clock(clock_source, flash_controller);
+
+        unlock(HSB_MASK_OFFSET);
+        PM_REGS.hsbmask.set(config.words[1]);
+        unlock(PBA_MASK_OFFSET);
+        PM_REGS.pbamask.set(config.words[2]);
+        unlock(PBB_MASK_OFFSET);
+        PM_REGS.pbbmask.set(config.words[3]);
+        unlock(PBC_MASK_OFFSET);
+        PM_REGS.pbcmask.set(config.words[4]);
+        unlock(PBD_MASK_OFFSET);
+        PM_REGS.pbdmask.set(config.words[5]);
+        Ok(())
+    }
+
+    /// The frequency of the main clock, which `get_system_frequency` is
+    /// derived from.
+    fn main_clock_frequency(&self) -> u32 {
+        match self.system_clock_source.get() {
+            // RC80M is always divided by 2 before it reaches the CPU.
+            SystemClockSource::RC80M => 80000000,
+            _ => self.get_system_frequency(),
+        }
+    }
+
     pub fn get_system_frequency(&self) -> u32 {
         // Return the current system frequency
         match self.system_clock_source.get() {
@@ -998,11 +1127,42 @@ fn unlock(register_offset: u32) {
     PM_REGS.unlock.set(0xAA000000 | register_offset);
 }
 
+/// Whether a peripheral bus clock is divided, and the exponent of the divider.
+fn bus_divider(select: &ReadWrite<u32, PeripheralBusXClockSelect::Register>) -> (bool, u32) {
+    (
+        select.is_set(PeripheralBusXClockSelect::PBDIV),
+        select.read(PeripheralBusXClockSelect::PBSEL),
+    )
+}
+
 fn select_main_clock(clock: MainClock) {
     unlock(0);
     PM_REGS.mcctrl.set(clock as u32);
 }
 
+/// Read the cause of the last reset from the RCAUSE register.
+///
+/// If several causes are flagged, e.g. a power-on reset also flags the
+/// brown-out detectors, the most fundamental one is returned.
+pub fn reset_reason() -> ResetReason {
+    let rcause = PM_REGS.rcause.extract();
+    if rcause.is_set(ResetCause::POR) || rcause.is_set(ResetCause::POR33) {
+        ResetReason::PowerOn
+    } else if rcause.is_set(ResetCause::BOD) || rcause.is_set(ResetCause::BOD33) {
+        ResetReason::Brownout
+    } else if rcause.is_set(ResetCause::WDT) {
+        ResetReason::Watchdog
+    } else if rcause.is_set(ResetCause::EXT) {
+        ResetReason::ExternalPin
+    } else if rcause.is_set(ResetCause::OCDRST) {
+        ResetReason::Software
+    } else if rcause.is_set(ResetCause::BKUP) {
+        ResetReason::Wakeup
+    } else {
+        ResetReason::Unknown
+    }
+}
+
 /// Utility macro to modify clock mask registers
 ///
 /// It takes one of two forms:
@@ -1139,6 +1299,45 @@ impl ClockInterface for Clock {
             Clock::PBD(v) => mask_clock!(PBD_MASK_OFFSET: pbdmask & !(1 << (v as u32))),
         }
     }
+
+    /// The frequency of the bus the clock is gated from: the main clock
+    /// divided by the CPU divider for HSB clocks, which run at the CPU
+    /// frequency, or by the divider of their bus for peripheral bus clocks.
+    fn frequency(&self) -> u32 {
+        let (divided, exponent) = match *self {
+            Clock::HSB(_) => (
+                PM_REGS.cpusel.is_set(CpuClockSelect::CPUDIV),
+                PM_REGS.cpusel.read(CpuClockSelect::CPUSEL),
+            ),
+            Clock::PBA(_) => bus_divider(&PM_REGS.pbasel),
+            Clock::PBB(_) => bus_divider(&PM_REGS.pbbsel),
+            Clock::PBC(_) => bus_divider(&PM_REGS.pbcsel),
+            Clock::PBD(_) => bus_divider(&PM_REGS.pbdsel),
+        };
+        let main = MAIN_CLOCK_HZ.load(Ordering::Relaxed);
+        if divided {
+            main >> (exponent + 1)
+        } else {
+            main
+        }
+    }
+
+    /// Peripheral bus clocks are gated from the clock of their APB bridge,
+    /// which is itself an HSB clock.
+    fn parent(&self) -> Option<&'static dyn ClockInterface> {
+        static APBA: Clock = Clock::HSB(HSBClock::APBA);
+        static APBB: Clock = Clock::HSB(HSBClock::APBB);
+        static APBC: Clock = Clock::HSB(HSBClock::APBC);
+        static APBD: Clock = Clock::HSB(HSBClock::APBD);
+
+        match *self {
+            Clock::HSB(_) => None,
+            Clock::PBA(_) => Some(&APBA),
+            Clock::PBB(_) => Some(&APBB),
+            Clock::PBC(_) => Some(&APBC),
+            Clock::PBD(_) => Some(&APBD),
+        }
+    }
 }
 
 pub fn enable_clock(clock: Clock) {
@@ -205,7 +205,6 @@ pub struct SpiHw<'a> {
     // Slave client is distinct from master client
     slave_client: OptionalCell<&'a dyn SpiSlaveClient>,
     role: Cell<SpiRole>,
-    pm: &'a pm::PowerManager,
 }
 
 const SPI_BASE: StaticRef<SpiRegisters> =
@@ -237,7 +236,7 @@ type SpiRegisterManager<'a, 'm> = PeripheralManager<'m, SpiHw<'a>, pm::Clock>;
 
 impl<'a> SpiHw<'a> {
     /// Creates a new SPI object, with peripheral 0 selected
-    pub const fn new(pm: &'a pm::PowerManager) -> SpiHw<'a> {
+    pub const fn new() -> SpiHw<'a> {
         SpiHw {
             client: OptionalCell::empty(),
             dma_read: OptionalCell::empty(),
@@ -247,7 +246,6 @@ impl<'a> SpiHw<'a> {
 
             slave_client: OptionalCell::empty(),
             role: Cell::new(SpiRole::SpiMaster),
-            pm,
         }
     }
 
@@ -303,7 +301,7 @@ impl<'a> SpiHw<'a> {
     /// Sets the approximate baud rate for the active peripheral,
     /// and return the actual baud rate set.
     ///
-    /// Since the only supported baud rates are (system clock / n) where n
+    /// Since the only supported baud rates are (SPI clock / n) where n
     /// is an integer from 1 to 255, the exact baud rate may not
     /// be available. In that case, the next lower baud rate will
     /// be selected.
@@ -311,9 +309,10 @@ impl<'a> SpiHw<'a> {
     /// The lowest available baud rate is 188235 baud. If the
     /// requested rate is lower, 188235 baud will be selected.
     pub fn set_baud_rate(&self, rate: u32) -> u32 {
-        // Main clock frequency
+        // The SPI is clocked by peripheral bus A, which may be divided down
+        // from the main clock.
         let mut real_rate = rate;
-        let clock = self.pm.get_system_frequency();
+        let clock = self.get_clock().frequency();
 
         if real_rate < 188235 {
             real_rate = 188235;
@@ -339,7 +338,7 @@ impl<'a> SpiHw<'a> {
 
     fn get_baud_rate(&self) -> u32 {
         let spi = &SpiRegisterManager::new(self);
-        let clock = 48000000;
+        let clock = self.get_clock().frequency();
         let scbr = self.get_active_csr(spi).read(ChipSelectParams::SCBR);
         clock / scbr
     }
@@ -478,29 +477,53 @@ impl<'a> SpiHw<'a> {
         // SPI's baud rate, transfer_done does not capture the interrupt
         // signaling the RX is done - may be due to missing the first read
         // byte when you start read after write.
-        read_buffer.map(|rbuf| {
+        if let Some(rbuf) = read_buffer {
             self.transfers_in_progress
                 .set(self.transfers_in_progress.get() + 1);
-            self.dma_read.map(move |read| {
+            if let Some(read) = self.dma_read.get() {
                 read.enable();
-                read.do_transfer(DMAPeripheral::SPI_RX, rbuf.take(), count);
-            });
-        });
+                if let Err((err, rbuf)) =
+                    read.do_transfer(DMAPeripheral::SPI_RX, rbuf.take(), count)
+                {
+                    self.cancel_transfer();
+                    return Err((err, write_buffer, Some(rbuf.into())));
+                }
+            }
+        }
 
         // The ordering of these operations matters.
         // For transfers 4 bytes or longer, this will work as expected.
         // For shorter transfers, the first byte will be missing.
-        write_buffer.map(|buf| {
+        if let Some(wbuf) = write_buffer {
             self.transfers_in_progress
                 .set(self.transfers_in_progress.get() + 1);
-            self.dma_write.map(move |write| {
+            if let Some(write) = self.dma_write.get() {
                 write.enable();
-                write.do_transfer(DMAPeripheral::SPI_TX, buf.take(), count);
-            });
-        });
+                if let Err((err, wbuf)) =
+                    write.do_transfer(DMAPeripheral::SPI_TX, wbuf.take(), count)
+                {
+                    // Take back the read buffer, if its transfer was started.
+                    let rbuf = self
+                        .dma_read
+                        .and_then(|read| read.abort_transfer())
+                        .map(|(buf, _)| buf.into());
+                    self.cancel_transfer();
+                    return Err((err, Some(wbuf.into()), rbuf));
+                }
+            }
+        }
 
         Ok(())
     }
+
+    /// Undo what `read_write_bytes` set up for a transfer it failed to start:
+    /// no transfer is outstanding anymore, so the SPI and its DMA channels
+    /// are disabled again.
+    fn cancel_transfer(&self) {
+        self.transfers_in_progress.set(0);
+        self.dma_length.set(0);
+        self.disable();
+    }
 }
 
 impl<'a> spi::SpiMaster<'a> for SpiHw<'a> {
@@ -743,13 +766,13 @@ impl DMAClient for SpiHw<'_> {
 
         if self.transfers_in_progress.get() == 0 {
             let txbuf = self.dma_write.map_or(None, |dma| {
-                let buf = dma.abort_transfer();
+                let buf = dma.abort_transfer().map(|(buf, _)| buf);
                 dma.disable();
                 buf
             });
 
             let rxbuf = self.dma_read.map_or(None, |dma| {
-                let buf = dma.abort_transfer();
+                let buf = dma.abort_transfer().map(|(buf, _)| buf);
                 dma.disable();
                 buf
             });
@@ -542,8 +542,10 @@ impl<'a> USART<'a> {
             // get buffer
             let mut length = 0;
             let mut buffer = self.rx_dma.get().and_then(|rx_dma| {
-                length = self.rx_len.get() - rx_dma.transfer_counter();
-                let buf = rx_dma.abort_transfer();
+                let buf = rx_dma.abort_transfer().map(|(buf, transferred)| {
+                    length = transferred;
+                    buf
+                });
                 rx_dma.disable();
                 buf
             });
@@ -571,8 +573,10 @@ impl<'a> USART<'a> {
             // get buffer
             let mut length = 0;
             let mut buffer = self.tx_dma.get().and_then(|tx_dma| {
-                length = self.tx_len.get() - tx_dma.transfer_counter();
-                let buf = tx_dma.abort_transfer();
+                let buf = tx_dma.abort_transfer().map(|(buf, transferred)| {
+                    length = transferred;
+                    buf
+                });
                 tx_dma.disable();
                 buf
             });
@@ -658,7 +662,7 @@ impl<'a> USART<'a> {
             // state machine, and clients cannot issue other USART calls from
             // the callback.
             let txbuffer = self.tx_dma.get().map_or(None, |tx_dma| {
-                let buf = tx_dma.abort_transfer();
+                let buf = tx_dma.abort_transfer().map(|(buf, _)| buf);
                 tx_dma.disable();
                 buf
             });
@@ -693,7 +697,7 @@ impl<'a> USART<'a> {
                         // Get the RX buffer, and it is ok if we didn't use one,
                         // we can just return None.
                         let rxbuf = self.rx_dma.get().map_or(None, |dma| {
-                            let buf = dma.abort_transfer();
+                            let buf = dma.abort_transfer().map(|(buf, _)| buf);
                             dma.disable();
                             buf
                         });
@@ -832,7 +836,7 @@ impl dma::DMAClient for USART<'_> {
 
                     // get buffer
                     let buffer = self.rx_dma.get().and_then(|rx_dma| {
-                        let buf = rx_dma.abort_transfer();
+                        let buf = rx_dma.abort_transfer().map(|(buf, _)| buf);
                         rx_dma.disable();
                         buf
                     });
@@ -912,8 +916,11 @@ impl<'a> uart::Receive<'a> for USART<'a> {
         if let Some(dma) = self.rx_dma.get() {
             dma.enable();
             self.rx_len.set(rx_len);
-            dma.do_transfer(self.rx_dma_peripheral, rx_buffer, rx_len);
-            Ok(())
+            dma.do_transfer(self.rx_dma_peripheral, rx_buffer, rx_len)
+                .inspect_err(|_| {
+                    self.disable_rx_interrupts(usart);
+                    self.disable_rx(usart);
+                })
         } else {
             Err((ErrorCode::OFF, rx_buffer))
         }
@@ -949,13 +956,11 @@ impl<'a> uart::Transmit<'a> for USART<'a> {
             self.usart_tx_state.set(USARTStateTX::DMA_Transmitting);
 
             // set up dma transfer and start transmission
-            if self.tx_dma.get().is_some() {
-                self.tx_dma.get().map(move |dma| {
-                    dma.enable();
-                    self.tx_len.set(tx_len);
-                    dma.do_transfer(self.tx_dma_peripheral, tx_buffer, tx_len);
-                });
-                Ok(())
+            if let Some(dma) = self.tx_dma.get() {
+                dma.enable();
+                self.tx_len.set(tx_len);
+                dma.do_transfer(self.tx_dma_peripheral, tx_buffer, tx_len)
+                    .inspect_err(|_| self.disable_tx(usart))
             } else {
                 Err((ErrorCode::OFF, tx_buffer))
             }
@@ -1031,9 +1036,10 @@ impl<'a> uart::ReceiveAdvanced<'a> for USART<'a> {
     ) -> Result<(), (ErrorCode, &'static mut [u8])> {
         if self.usart_rx_state.get() != USARTStateRX::Idle {
             Err((ErrorCode::BUSY, rx_buffer))
+        } else if len > rx_buffer.len() {
+            Err((ErrorCode::SIZE, rx_buffer))
         } else {
             let usart = &USARTRegManager::new(self);
-            let length = cmp::min(len, rx_buffer.len());
 
             // enable RX
             self.enable_rx(usart);
@@ -1044,11 +1050,16 @@ impl<'a> uart::ReceiveAdvanced<'a> for USART<'a> {
             self.enable_rx_timeout(usart, interbyte_timeout);
 
             // set up dma transfer and start reception
-            self.rx_dma.get().map(move |dma| {
+            if let Some(dma) = self.rx_dma.get() {
                 dma.enable();
-                dma.do_transfer(self.rx_dma_peripheral, rx_buffer, length);
-                self.rx_len.set(length);
-            });
+                if let Err(err) = dma.do_transfer(self.rx_dma_peripheral, rx_buffer, len) {
+                    self.disable_rx_timeout(usart);
+                    self.disable_rx_interrupts(usart);
+                    self.disable_rx(usart);
+                    return Err(err);
+                }
+                self.rx_len.set(len);
+            }
             Ok(())
         }
     }
@@ -1125,36 +1136,63 @@ impl<'a> spi::SpiMaster<'a> for USART<'a> {
             },
         );
 
+        // If a transfer cannot be set up, release !CS again before handing
+        // the buffers back.
+        let deassert_cs = || {
+            self.spi_chip_select.map_or_else(
+                || {
+                    self.rts_disable_spi_deassert_cs(usart);
+                },
+                |cs| {
+                    cs.deactivate();
+                },
+            );
+        };
+
         // Check if we should read and write or just write.
-        if read_buffer.is_some() {
+        if let Some(rbuf) = read_buffer {
             // We are reading and writing.
-            read_buffer.map(|rbuf| {
-                self.tx_dma.get().map(move |dma| {
-                    self.rx_dma.get().map(move |read| {
-                        // Do all the maps before starting anything in case
-                        // they take too much time.
-
-                        // Start the write transaction.
-                        self.usart_tx_state.set(USARTStateTX::DMA_Transmitting);
-                        self.usart_rx_state.set(USARTStateRX::Idle);
-                        dma.enable();
-                        dma.do_transfer(self.tx_dma_peripheral, write_buffer.take(), count);
-
-                        // Start the read transaction.
-                        self.usart_rx_state.set(USARTStateRX::DMA_Receiving);
-                        read.enable();
-                        read.do_transfer(self.rx_dma_peripheral, rbuf.take(), count);
-                    });
-                });
-            });
+            if let (Some(dma), Some(read)) = (self.tx_dma.get(), self.rx_dma.get()) {
+                // Prepare both transfers before starting anything in case
+                // this takes too much time, or one of them cannot be set up.
+                read.enable();
+                if let Err((err, rbuf)) =
+                    read.prepare_transfer(self.rx_dma_peripheral, rbuf.take(), count)
+                {
+                    deassert_cs();
+                    return Err((err, write_buffer, Some(rbuf.into())));
+                }
+                dma.enable();
+                if let Err((err, wbuf)) =
+                    dma.prepare_transfer(self.tx_dma_peripheral, write_buffer.take(), count)
+                {
+                    deassert_cs();
+                    let rbuf = read.abort_transfer().map(|(buf, _)| buf.into());
+                    return Err((err, wbuf.into(), rbuf));
+                }
+
+                // Start the write transaction.
+                self.usart_tx_state.set(USARTStateTX::DMA_Transmitting);
+                self.usart_rx_state.set(USARTStateRX::Idle);
+                dma.start_transfer();
+
+                // Start the read transaction.
+                self.usart_rx_state.set(USARTStateRX::DMA_Receiving);
+                read.start_transfer();
+            }
         } else {
             // We are just writing.
-            self.tx_dma.get().map(move |dma| {
+            if let Some(dma) = self.tx_dma.get() {
+                dma.enable();
+                if let Err((err, wbuf)) =
+                    dma.do_transfer(self.tx_dma_peripheral, write_buffer.take(), count)
+                {
+                    deassert_cs();
+                    return Err((err, wbuf.into(), None));
+                }
                 self.usart_tx_state.set(USARTStateTX::DMA_Transmitting);
                 self.usart_rx_state.set(USARTStateRX::Idle);
-                dma.enable();
-                dma.do_transfer(self.tx_dma_peripheral, write_buffer.take(), count);
-            });
+            }
         }
 
         Ok(())
@@ -6,10 +6,14 @@
 
 use core::cell::Cell;
 
+use crate::ast::Ast;
 use crate::pm::{self, Clock, PBDClock};
 
 use cortexm4::support;
 
+use kernel::hil::time::{ConvertTicks, Ticks, Time};
+use kernel::platform::watchdog::WatchDogClient;
+use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::math::log_base_two_u64;
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{
@@ -104,12 +108,43 @@ register_bitfields![u32,
     ]
 ];
 
+/// The client of the watchdog warning, see `warning_handler`.
+struct WarningClient(Cell<Option<&'static dyn WatchDogClient>>);
+
+// Safety: the kernel is single threaded. The client is only set before the
+// warning interrupt is enabled, and only read from the interrupt handler.
+unsafe impl Sync for WarningClient {}
+
+static WARNING_CLIENT: WarningClient = WarningClient(Cell::new(None));
+
+/// Handler for the WDT interrupt, installed in place of the generic interrupt
+/// handler so that the warning is delivered even if the kernel loop is hung.
+///
+/// The interrupt is masked, but its flag is left set, so that the watchdog
+/// still resets the chip if the client returns.
+pub unsafe extern "C" fn warning_handler() {
+    WDT_REGS.idr.write(Interrupt::WINT::SET);
+    if let Some(client) = WARNING_CLIENT.0.get() {
+        client.watchdog_warning();
+    }
+}
+
+/// Watchdog period used by the kernel loop.
+const PERIOD_MS: usize = 100;
+
+/// Watchdog period used by `with_extended_timeout()`.
+const EXTENDED_PERIOD_MS: usize = 10_000;
+
 // Page 59 of SAM4L data sheet
 const WDT_BASE: *mut WdtRegisters = 0x400F0C00 as *mut WdtRegisters;
 const WDT_REGS: StaticRef<WdtRegisters> = unsafe { StaticRef::new(WDT_BASE.cast_const()) };
 
 pub struct Wdt {
     enabled: Cell<bool>,
+    /// Low-frequency time source used to timestamp each tickle.
+    ast: OptionalCell<&'static Ast<'static>>,
+    /// AST time of the most recent tickle.
+    last_pet: OptionalCell<<Ast<'static> as Time>::Ticks>,
 }
 
 #[derive(Copy, Clone)]
@@ -131,9 +166,17 @@ impl Wdt {
     pub const fn new() -> Wdt {
         Wdt {
             enabled: Cell::new(false),
+            ast: OptionalCell::empty(),
+            last_pet: OptionalCell::empty(),
         }
     }
 
+    /// Set the AST used to track the time since the watchdog was last
+    /// tickled. Without it, `time_since_last_pet()` returns `None`.
+    pub fn set_time_source(&self, ast: &'static Ast<'static>) {
+        self.ast.set(ast);
+    }
+
     /// WDT Errata: §45.1.3
     ///
     /// When writing any of the PSEL, TBAN, EN, or MODE fields, must insert a
@@ -203,10 +246,21 @@ impl Wdt {
         let mult: u64 = f_clk_khz * (period as u64);
         let scaler = log_base_two_u64(mult); // prefer rounding for longer WD (thus no -1)
 
+        // In interrupt mode the first time-out raises WINT instead of
+        // resetting the chip, and the reset follows at the next time-out
+        // unless the interrupt has been cleared.
+        let interrupt_mode = if WARNING_CLIENT.0.get().is_some() {
+            WDT_REGS.ier.write(Interrupt::WINT::SET);
+            Control::IM::InterruptModeEnabled
+        } else {
+            Control::IM::InterruptModeDisabled
+        };
+
         let control = Control::CEN::ClockEnable
             + Control::PSEL.val(scaler)
             + Control::FCD::DoNotRedoCalibration
             + Control::DAR::DisableAfterReset
+            + interrupt_mode
             + Control::EN::Enable;
         self.write_cr(control);
     }
@@ -223,13 +277,14 @@ impl Wdt {
         // Need to write the WDTCLR bit twice for it to work
         WDT_REGS.clr.write(Clear::KEY::KEY1 + Clear::WDTCLR::SET);
         WDT_REGS.clr.write(Clear::KEY::KEY2 + Clear::WDTCLR::SET);
+
+        self.ast.map(|ast| self.last_pet.set(ast.now()));
     }
 }
 
 impl kernel::platform::watchdog::WatchDog for Wdt {
     fn setup(&self) {
-        // Setup the WatchDog with a 100ms period.
-        self.start(100);
+        self.start(PERIOD_MS);
     }
 
     fn tickle(&self) {
@@ -239,4 +294,39 @@ impl kernel::platform::watchdog::WatchDog for Wdt {
     fn suspend(&self) {
         self.stop();
     }
+
+    /// `suspend()` stops the watchdog and gates its clock, so it is started
+    /// again rather than only tickled.
+    fn resume(&self) {
+        self.start(PERIOD_MS);
+        self.tickle();
+    }
+
+    /// The warning is given one watchdog period, i.e. 100ms, before the
+    /// reset. It must be set up before `setup()` is called.
+    fn on_warning(&self, client: &'static dyn WatchDogClient) {
+        WARNING_CLIENT.0.set(Some(client));
+    }
+
+    /// The timeout is lengthened to 10s while `f` runs. The watchdog is
+    /// briefly stopped around each change of the period, so that it is never
+    /// running with a partially written configuration.
+    fn with_extended_timeout<R>(&self, f: impl FnOnce() -> R) -> R {
+        if !self.enabled.get() {
+            return f();
+        }
+        self.stop();
+        self.start(EXTENDED_PERIOD_MS);
+        let result = f();
+        self.stop();
+        self.start(PERIOD_MS);
+        self.tickle();
+        result
+    }
+
+    fn time_since_last_pet(&self) -> Option<u32> {
+        let ast = self.ast.get()?;
+        let last_pet = self.last_pet.get()?;
+        Some(ast.ticks_to_ms(ast.now().wrapping_sub(last_pet)))
+    }
 }
@@ -131,6 +131,18 @@ impl<F: Frequency> kernel::platform::scheduler_timer::SchedulerTimer for Clint<'
         }
     }
 
+    fn max_timeslice_us(&self) -> u32 {
+        // `get_remaining_us()` treats an alarm one second or more away as
+        // expired.
+        999_999
+    }
+
+    fn remaining_us(&self) -> Option<u32> {
+        // Reading the timer has no side effects, and after a reset the compare
+        // value is far enough away that `get_remaining_us()` reports `None`.
+        self.get_remaining_us().map(NonZeroU32::get)
+    }
+
     fn reset(&self) {
         self.disable_machine_timer();
     }
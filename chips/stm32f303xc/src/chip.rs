@@ -7,6 +7,7 @@
 use core::fmt::Write;
 use cortexm4f::{CortexM4F, CortexMVariant};
 use kernel::platform::chip::Chip;
+use kernel::platform::chip::CriticalToken;
 use kernel::platform::chip::InterruptService;
 
 use crate::nvic;
@@ -148,6 +149,13 @@ impl<'a, I: InterruptService + 'a> Chip for Stm32f3xx<'a, I> {
         cortexm4f::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            cortexm4f::support::disable_interrupts(),
+            cortexm4f::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, write: &mut dyn Write) {
         CortexM4F::print_cortexm_state(write);
     }
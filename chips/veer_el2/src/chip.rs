@@ -8,7 +8,7 @@
 use crate::machine_timer::Clint;
 use core::fmt::Write;
 use core::ptr::addr_of;
-use kernel::platform::chip::{Chip, InterruptService};
+use kernel::platform::chip::{Chip, CriticalToken, InterruptService};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable};
 use kernel::utilities::StaticRef;
 use rv32i::csr::{mcause, mie::mie, mip::mip, CSR};
@@ -145,6 +145,13 @@ impl<'a, I: InterruptService + 'a> kernel::platform::chip::Chip for VeeR<'a, I>
         rv32i::support::atomic(f)
     }
 
+    unsafe fn enter_critical(&self) -> CriticalToken {
+        CriticalToken::new(
+            rv32i::support::disable_interrupts(),
+            rv32i::support::restore_interrupts,
+        )
+    }
+
     unsafe fn print_state(&self, writer: &mut dyn Write) {
         rv32i::print_riscv_state(writer);
     }
@@ -50,8 +50,9 @@ use core::str;
 
 use crate::collections::queue::Queue;
 use crate::collections::ring_buffer::RingBuffer;
+use crate::config;
 use crate::hil;
-use crate::platform::chip::Chip;
+use crate::platform::chip::{Chip, StateDumpLevel};
 use crate::process::Process;
 use crate::process::ProcessPrinter;
 use crate::processbuffer::ReadableProcessSlice;
@@ -184,12 +185,156 @@ pub unsafe fn panic_banner<W: Write>(writer: &mut W, panic_info: &PanicInfo) {
 pub unsafe fn panic_cpu_state<W: Write, C: Chip>(
     chip: &'static Option<&'static C>,
     writer: &mut W,
+) {
+    // Builds that leave out panic debugging output also only print the
+    // minimal chip state.
+    let level = if config::CONFIG.debug_panics {
+        StateDumpLevel::Normal
+    } else {
+        StateDumpLevel::Minimal
+    };
+    panic_cpu_state_at_level(chip, writer, level);
+}
+
+/// Print current machine (CPU) state with the amount of detail selected by
+/// `level`.
+///
+/// **NOTE:** The supplied `writer` must be synchronous.
+pub unsafe fn panic_cpu_state_at_level<W: Write, C: Chip>(
+    chip: &'static Option<&'static C>,
+    writer: &mut W,
+    level: StateDumpLevel,
 ) {
     chip.map(|c| {
-        c.print_state(writer);
+        c.print_state_at_level(writer, level);
     });
 }
 
+/// Broad cause of a panic, as determined by a `PanicClassifier`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PanicClass {
+    /// The cause could not be determined.
+    Unknown = 0,
+    /// A kernel invariant or assertion was violated.
+    KernelAssertion = 1,
+    /// A driver or capsule detected an unrecoverable error.
+    DriverFault = 2,
+    /// The CPU raised a fault exception in the kernel.
+    HardwareFault = 3,
+    /// The kernel ran out of memory, e.g. for grants or static buffers.
+    OutOfMemory = 4,
+}
+
+impl PanicClass {
+    fn from_u8(value: u8) -> PanicClass {
+        match value {
+            1 => PanicClass::KernelAssertion,
+            2 => PanicClass::DriverFault,
+            3 => PanicClass::HardwareFault,
+            4 => PanicClass::OutOfMemory,
+            _ => PanicClass::Unknown,
+        }
+    }
+}
+
+/// Board-specific categorization of panics, e.g. for telemetry.
+///
+/// Boards know which panic messages and locations belong to which of their
+/// drivers, so the board's panic handler passes its classifier to
+/// `panic_classify` alongside the textual dump.
+pub trait PanicClassifier {
+    /// Return the class of the panic described by `panic_info`. The default
+    /// implementation returns `PanicClass::Unknown`.
+    fn classify_panic(&self, _panic_info: &PanicInfo) -> PanicClass {
+        PanicClass::Unknown
+    }
+}
+
+/// Classifier that puts every panic in `PanicClass::Unknown`.
+impl PanicClassifier for () {}
+
+/// The classes of the last `PANIC_CLASS_LOG_LEN` panics.
+///
+/// To let a recovery app read the classes after the reboot that follows a
+/// panic, boards place this in RAM that the startup code does not
+/// initialize. That RAM holds garbage after a power-on reset, which the log
+/// detects and discards before recording.
+#[repr(C)]
+pub struct PanicClassLog {
+    magic: u32,
+    count: u32,
+    classes: [u8; PANIC_CLASS_LOG_LEN],
+}
+
+/// Number of panics remembered by `PanicClassLog`.
+pub const PANIC_CLASS_LOG_LEN: usize = 4;
+
+impl PanicClassLog {
+    /// Value of `magic` once the log has been initialized.
+    const MAGIC: u32 = 0x7061_6e63;
+
+    pub const fn new() -> PanicClassLog {
+        PanicClassLog {
+            magic: Self::MAGIC,
+            count: 0,
+            classes: [PanicClass::Unknown as u8; PANIC_CLASS_LOG_LEN],
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.magic == Self::MAGIC
+    }
+
+    /// Add `class` to the log, dropping the oldest class if it is full.
+    pub fn record(&mut self, class: PanicClass) {
+        if !self.is_valid() {
+            *self = PanicClassLog::new();
+        }
+        self.classes[self.count as usize % PANIC_CLASS_LOG_LEN] = class as u8;
+        self.count = self.count.wrapping_add(1);
+    }
+
+    /// Total number of panics recorded, including those no longer in the
+    /// log.
+    pub fn count(&self) -> u32 {
+        if self.is_valid() {
+            self.count
+        } else {
+            0
+        }
+    }
+
+    /// The class of the `n`th most recent panic, 0 being the last one, or
+    /// `None` if the log does not hold that many.
+    pub fn get(&self, n: usize) -> Option<PanicClass> {
+        let count = self.count() as usize;
+        if n >= PANIC_CLASS_LOG_LEN || n >= count {
+            return None;
+        }
+        let index = (count - 1 - n) % PANIC_CLASS_LOG_LEN;
+        Some(PanicClass::from_u8(self.classes[index]))
+    }
+}
+
+/// Classify the current panic with `classifier`, print the class, and add it
+/// to `log` if one is given.
+///
+/// **NOTE:** The supplied `writer` must be synchronous.
+pub unsafe fn panic_classify<W: Write>(
+    classifier: &dyn PanicClassifier,
+    panic_info: &PanicInfo,
+    log: Option<&mut PanicClassLog>,
+    writer: &mut W,
+) -> PanicClass {
+    let class = classifier.classify_panic(panic_info);
+    let _ = writer.write_fmt(format_args!("\tPanic class: {:?}\r\n", class));
+    if let Some(log) = log {
+        log.record(class);
+    }
+    class
+}
+
 /// More detailed prints about all processes.
 ///
 /// **NOTE:** The supplied `writer` must be synchronous.
@@ -6,10 +6,290 @@
 
 use crate::ErrorCode;
 
+/// Frequency of the tone played by the default `Buzzer::self_test()`.
+pub const SELF_TEST_FREQUENCY_HZ: usize = 1000;
+/// Duration of the tone played by the default `Buzzer::self_test()`.
+pub const SELF_TEST_DURATION_MS: usize = 100;
+
+/// Frequency of the pulse played by `Buzzer::click()`.
+pub const CLICK_FREQUENCY_HZ: usize = 4000;
+/// Length of the pulse played by `Buzzer::click()`.
+pub const CLICK_DURATION_US: u32 = 250;
+
+/// Slowest tempo, in beats per minute, accepted by `Buzzer::start_metronome()`.
+pub const METRONOME_MIN_BPM: usize = 20;
+/// Fastest tempo, in beats per minute, accepted by `Buzzer::start_metronome()`.
+pub const METRONOME_MAX_BPM: usize = 300;
+
+/// Frequencies in hertz, rounded to the nearest integer, of the notes of
+/// `Note`, in the same order. Pitches follow twelve-tone equal temperament
+/// with A4 at 440 Hz.
+pub const NOTE_FREQUENCIES_HZ: [usize; 24] = [
+    262, // C4
+    277, // Cs4
+    294, // D4
+    311, // Ds4
+    330, // E4
+    349, // F4
+    370, // Fs4
+    392, // G4
+    415, // Gs4
+    440, // A4
+    466, // As4
+    494, // B4
+    523, // C5
+    554, // Cs5
+    587, // D5
+    622, // Ds5
+    659, // E5
+    698, // F5
+    740, // Fs5
+    784, // G5
+    831, // Gs5
+    880, // A5
+    932, // As5
+    988, // B5
+];
+
+/// A musical pitch, for `Buzzer::buzz_note()`. `s` stands for sharp, e.g.
+/// `Cs4` is C sharp in the fourth octave.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Note {
+    C4,
+    Cs4,
+    D4,
+    Ds4,
+    E4,
+    F4,
+    Fs4,
+    G4,
+    Gs4,
+    A4,
+    As4,
+    B4,
+    C5,
+    Cs5,
+    D5,
+    Ds5,
+    E5,
+    F5,
+    Fs5,
+    G5,
+    Gs5,
+    A5,
+    As5,
+    B5,
+}
+
+impl Note {
+    /// The frequency of this note, from `NOTE_FREQUENCIES_HZ`.
+    pub fn frequency_hz(&self) -> usize {
+        NOTE_FREQUENCIES_HZ[*self as usize]
+    }
+}
+
+/// The length of a note relative to the beat, for `Buzzer::buzz_note()`. A
+/// beat is a quarter note.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoteValue {
+    Whole,
+    Half,
+    Quarter,
+    Eighth,
+    Sixteenth,
+}
+
+impl NoteValue {
+    /// The duration in milliseconds of a note of this length at `tempo_bpm`
+    /// quarter notes per minute. `tempo_bpm` must not be 0.
+    pub fn duration_ms(&self, tempo_bpm: usize) -> usize {
+        let sixteenths = match self {
+            NoteValue::Whole => 16,
+            NoteValue::Half => 8,
+            NoteValue::Quarter => 4,
+            NoteValue::Eighth => 2,
+            NoteValue::Sixteenth => 1,
+        };
+        60_000 * sixteenths / 4 / tempo_bpm
+    }
+}
+
+/// The notes of a melody in RTTTL, the Nokia ringtone format, for
+/// `Buzzer::play_rtttl()`.
+///
+/// A tune has the form `name:d=4,o=5,b=120:8c6,8d#,e.,p`: a name, the default
+/// duration, octave and tempo in beats per minute, then the notes. Each note
+/// is an optional duration (1, 2, 4, 8, 16 or 32, i.e. a whole note to a
+/// thirty-second note), a pitch from `a` to `g`, `h` (the same as `b`) or `p`
+/// for a rest, an optional `#` for sharp, an optional octave from 4 to 7 and
+/// an optional `.` which makes the note half as long again. Missing defaults
+/// are `d=4`, `o=6` and `b=63`.
+///
+/// `next_note()` returns each note as a `(frequency_hz, duration_ms)` pair, like
+/// those of `Buzzer::buzz_sequence()`, with a frequency of 0 for rests, or
+/// `INVAL` for a malformed note.
+#[derive(Copy, Clone, Debug)]
+pub struct RtttlNotes<'a> {
+    /// The notes not yet returned.
+    notes: &'a str,
+    duration: usize,
+    octave: usize,
+    bpm: usize,
+}
+
+impl<'a> RtttlNotes<'a> {
+    /// Parse the name and defaults of `tune`.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(RtttlNotes)`: The notes of `tune`, which are parsed one at a time
+    ///   by `next_note()`.
+    /// - `INVAL`: `tune` does not have three sections, or a default is
+    ///   unknown or out of range.
+    pub fn new(tune: &'a str) -> Result<Self, ErrorCode> {
+        let (_name, rest) = tune.split_once(':').ok_or(ErrorCode::INVAL)?;
+        let (defaults, notes) = rest.split_once(':').ok_or(ErrorCode::INVAL)?;
+        let mut parsed = RtttlNotes {
+            notes,
+            duration: 4,
+            octave: 6,
+            bpm: 63,
+        };
+        for default in defaults.split(',').map(str::trim) {
+            if default.is_empty() {
+                continue;
+            }
+            let (key, value) = default.split_once('=').ok_or(ErrorCode::INVAL)?;
+            let value = parse_number(value.trim()).ok_or(ErrorCode::INVAL)?;
+            match key.trim() {
+                "d" | "D" => parsed.duration = check_duration(value)?,
+                "o" | "O" => parsed.octave = check_octave(value)?,
+                "b" | "B" if (1..=900).contains(&value) => parsed.bpm = value,
+                _ => return Err(ErrorCode::INVAL),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Parse the next note, or return `None` once all notes have been
+    /// returned.
+    pub fn next_note(&mut self) -> Option<Result<(usize, usize), ErrorCode>> {
+        let notes = self.notes.trim_start();
+        if notes.is_empty() {
+            self.notes = notes;
+            return None;
+        }
+        let (note, rest) = notes.split_once(',').unwrap_or((notes, ""));
+        self.notes = rest;
+        Some(self.parse_note(note.trim()))
+    }
+
+    /// Parse a single note, without surrounding whitespace.
+    fn parse_note(&self, text: &str) -> Result<(usize, usize), ErrorCode> {
+        let note = text.as_bytes();
+
+        let digits = note.iter().take_while(|c| c.is_ascii_digit()).count();
+        let duration = match digits {
+            0 => self.duration,
+            _ => check_duration(parse_number(&text[..digits]).ok_or(ErrorCode::INVAL)?)?,
+        };
+        let mut i = digits;
+
+        // Semitones above C, or `None` for a rest.
+        let mut semitone = match note.get(i).map(u8::to_ascii_lowercase) {
+            Some(b'c') => Some(0),
+            Some(b'd') => Some(2),
+            Some(b'e') => Some(4),
+            Some(b'f') => Some(5),
+            Some(b'g') => Some(7),
+            Some(b'a') => Some(9),
+            Some(b'b') | Some(b'h') => Some(11),
+            Some(b'p') => None,
+            _ => return Err(ErrorCode::INVAL),
+        };
+        i += 1;
+
+        if note.get(i) == Some(&b'#') {
+            semitone = semitone.map(|s| s + 1);
+            i += 1;
+        }
+
+        // The dot may come before or after the octave.
+        let mut dotted = false;
+        if note.get(i) == Some(&b'.') {
+            dotted = true;
+            i += 1;
+        }
+        let mut octave = self.octave;
+        if let Some(c) = note.get(i).filter(|c| c.is_ascii_digit()) {
+            octave = check_octave((c - b'0') as usize)?;
+            i += 1;
+        }
+        if !dotted && note.get(i) == Some(&b'.') {
+            dotted = true;
+            i += 1;
+        }
+        if i != note.len() {
+            return Err(ErrorCode::INVAL);
+        }
+
+        // The table covers octaves 4 and 5, and each octave above doubles
+        // the frequency.
+        let frequency_hz = semitone.map_or(0, |semitone| {
+            let n = (octave - 4) * 12 + semitone;
+            if n < NOTE_FREQUENCIES_HZ.len() {
+                NOTE_FREQUENCIES_HZ[n]
+            } else {
+                NOTE_FREQUENCIES_HZ[12 + n % 12] << (n / 12 - 1)
+            }
+        });
+        // A whole note is four beats.
+        let mut duration_ms = 240_000 / self.bpm / duration;
+        if dotted {
+            duration_ms += duration_ms / 2;
+        }
+        Ok((frequency_hz, duration_ms))
+    }
+}
+
+/// Parse a decimal number of at most four digits, so it cannot overflow.
+fn parse_number(digits: &str) -> Option<usize> {
+    if digits.is_empty() || digits.len() > 4 {
+        return None;
+    }
+    digits.bytes().try_fold(0, |n, c| {
+        c.is_ascii_digit().then(|| n * 10 + (c - b'0') as usize)
+    })
+}
+
+fn check_duration(duration: usize) -> Result<usize, ErrorCode> {
+    match duration {
+        1 | 2 | 4 | 8 | 16 | 32 => Ok(duration),
+        _ => Err(ErrorCode::INVAL),
+    }
+}
+
+fn check_octave(octave: usize) -> Result<usize, ErrorCode> {
+    match octave {
+        4..=7 => Ok(octave),
+        _ => Err(ErrorCode::INVAL),
+    }
+}
+
 pub trait BuzzerClient {
     /// Called when the current sound played by the buzzer has finished
     /// or it was stopped.
     fn buzzer_done(&self, status: Result<(), ErrorCode>);
+
+    /// Called instead of `buzzer_done()` by buzzers with several outputs when
+    /// the sound started with `Buzzer::buzz_channel()` on `channel` has
+    /// finished or was stopped.
+    ///
+    /// The default implementation ignores the channel and calls
+    /// `buzzer_done()`.
+    fn buzzer_channel_done(&self, _channel: usize, status: Result<(), ErrorCode>) {
+        self.buzzer_done(status)
+    }
 }
 
 /// The Buzzer HIL is used to play a sound on a buzzer at a fixed frequency and
@@ -21,12 +301,221 @@ pub trait Buzzer<'a> {
     /// If it is called while the buzzer is playing, the buzzer command will be
     /// overridden with the new frequency and duration values.
     ///
+    /// A `duration_ms` of 0 plays the sound continuously until `stop()` is
+    /// called (or it is overridden by another call), and `buzzer_done()` is
+    /// not called until then.
+    ///
     /// Return values:
     ///
     /// - `Ok(())`: The attempt at starting the buzzer was successful.
     /// - `FAIL`: Cannot start the buzzer.
     fn buzz(&self, frequency_hz: usize, duration_ms: usize) -> Result<(), ErrorCode>;
 
+    /// Return how many outputs, e.g. separate piezo elements, the buzzer
+    /// can drive independently with `buzz_channel()`.
+    ///
+    /// The default implementation returns 1.
+    fn num_channels(&self) -> usize {
+        1
+    }
+
+    /// Play a sound like `buzz()`, but on output `channel` only, so that
+    /// buzzers with several outputs can play different tones at the same
+    /// time. Sounds on other channels keep playing. Once the sound finishes,
+    /// the `buzzer_channel_done()` callback is called with `channel`.
+    ///
+    /// Channel 0 is the output used by `buzz()`. The default implementation
+    /// calls `buzz()` for channel 0, whose completion is reported through
+    /// `buzzer_done()`, and rejects any other channel.
+    ///
+    /// Return values are the same as for `buzz()`, and additionally:
+    ///
+    /// - `INVAL`: `channel` is not less than `num_channels()`.
+    fn buzz_channel(
+        &self,
+        channel: usize,
+        frequency_hz: usize,
+        duration_ms: usize,
+    ) -> Result<(), ErrorCode> {
+        if channel != 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        self.buzz(frequency_hz, duration_ms)
+    }
+
+    /// Play a musical note like `buzz()`, with the frequency of `note` and
+    /// the duration of `duration` at `tempo_bpm` quarter notes per minute.
+    /// Once the note finishes, the `buzzer_done()` callback is called.
+    ///
+    /// The default implementation converts the note with
+    /// `Note::frequency_hz()` and `NoteValue::duration_ms()` and calls
+    /// `buzz()`.
+    ///
+    /// Return values are the same as for `buzz()`, and additionally:
+    ///
+    /// - `INVAL`: `tempo_bpm` is 0.
+    fn buzz_note(
+        &self,
+        note: Note,
+        duration: NoteValue,
+        tempo_bpm: usize,
+    ) -> Result<(), ErrorCode> {
+        if tempo_bpm == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        self.buzz(note.frequency_hz(), duration.duration_ms(tempo_bpm))
+    }
+
+    /// Play a sequence of notes back to back. Each note is a
+    /// `(frequency_hz, duration_ms)` pair. Once the last note finishes
+    /// playing, the `buzzer_done()` callback is called once for the whole
+    /// sequence. As with `buzz()`, a note with a duration of 0 plays until
+    /// `stop()` is called. Calling `stop()` cancels the rest of the sequence, and
+    /// calling `buzz()` or `buzz_sequence()` replaces it.
+    ///
+    /// Implementations that cannot queue notes may start only the first note
+    /// and return `NOSUPPORT`. In that case `buzzer_done()` is called when that
+    /// note finishes. This is what the default implementation does.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The attempt at starting the sequence was successful.
+    /// - `INVAL`: `notes` is empty, nothing is played.
+    /// - `NOSUPPORT`: Only the first note of the sequence is played.
+    /// - `FAIL`: Cannot start the buzzer.
+    fn buzz_sequence(&self, notes: &'static [(usize, usize)]) -> Result<(), ErrorCode> {
+        let (frequency_hz, duration_ms) = notes.first().ok_or(ErrorCode::INVAL)?;
+        self.buzz(*frequency_hz, *duration_ms)?;
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    /// Play a melody written in RTTTL, see `RtttlNotes`, note by note like
+    /// `buzz_sequence()`. Once the last note finishes playing, the
+    /// `buzzer_done()` callback is called. Calling `stop()` cancels the rest
+    /// of the melody, and calling `buzz()` or `buzz_sequence()` replaces it.
+    ///
+    /// The whole tune is parsed before anything is played, so a malformed
+    /// note cannot cut the melody short.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The melody is playing.
+    /// - `INVAL`: `tune` is malformed or has no notes, nothing is played.
+    /// - `NOSUPPORT`: The buzzer cannot queue notes. This is what the default
+    ///   implementation returns.
+    /// - `FAIL`: Cannot start the buzzer.
+    fn play_rtttl(&self, _tune: &'static str) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    /// Play a waveform table: output each of `samples` in turn at
+    /// `sample_rate` samples per second, e.g. by feeding a DAC through DMA.
+    /// Samples are unsigned, with `u16::MAX` being the highest output level;
+    /// implementations with a narrower output drop the low bits. Once the last
+    /// sample has been output, the `buzzer_done()` callback is called. Calling
+    /// `stop()` cancels playback, and calling `buzz()` or `buzz_sequence()`
+    /// replaces it.
+    ///
+    /// The default implementation returns `NOSUPPORT`, for buzzers that can
+    /// only play square waves.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The attempt at starting playback was successful.
+    /// - `INVAL`: `samples` is empty or `sample_rate` is 0 or too high for the
+    ///   buzzer.
+    /// - `NOSUPPORT`: The buzzer cannot play waveforms.
+    /// - `FAIL`: Cannot start the buzzer.
+    fn buzz_waveform(
+        &self,
+        _samples: &'static [u16],
+        _sample_rate: usize,
+    ) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    /// Emit a short beep at `beep_freq_hz` on every beat of a steady tempo of
+    /// `bpm` beats per minute, until `stop()` is called. The beats are timed
+    /// by the buzzer itself, so they do not jitter with process scheduling.
+    /// The `buzzer_done()` callback is only called once the metronome is
+    /// stopped, not for each beat. Calling `buzz()`, `buzz_sequence()` or
+    /// `start_metronome()` again replaces the metronome.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The metronome was started.
+    /// - `INVAL`: `bpm` is outside of `METRONOME_MIN_BPM` to
+    ///   `METRONOME_MAX_BPM`.
+    /// - `NOSUPPORT`: The buzzer cannot time beats itself. This is what the
+    ///   default implementation returns.
+    /// - `FAIL`: Cannot start the buzzer.
+    fn start_metronome(&self, _bpm: usize, _beep_freq_hz: usize) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    /// Emit a single short click, roughly a `CLICK_DURATION_US` long pulse at
+    /// `CLICK_FREQUENCY_HZ`, e.g. as feedback for a button press. This
+    /// returns immediately and `buzzer_done()` is not called for it, so it is
+    /// cheap enough to call dozens of times per second.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The click is playing.
+    /// - `BUSY`: The buzzer is playing another sound, which a click does not
+    ///   interrupt.
+    /// - `FAIL`: Cannot start the buzzer.
+    /// - `NOSUPPORT`: The buzzer cannot click. This is what the default
+    ///   implementation returns.
+    fn click(&self) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    /// Set the volume of the buzzer as a percentage from 0 (silent) to 100
+    /// (loudest). The volume persists across subsequent calls to `buzz()` and
+    /// `buzz_sequence()` until it is changed again. Whether it also applies
+    /// to a sound that is currently playing is implementation specific.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The volume was set.
+    /// - `INVAL`: `percent` is greater than 100.
+    /// - `NOSUPPORT`: The buzzer cannot vary its volume (e.g. it is driven by
+    ///   toggling a GPIO pin). This is what the default implementation
+    ///   returns.
+    fn set_volume(&self, _percent: u8) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    /// Mute or unmute the buzzer. While muted, calls such as `buzz()` succeed
+    /// and keep their timing, so `buzzer_done()` is called on schedule, but
+    /// no sound is produced. Unlike `stop()`, muting does not end the sound
+    /// currently playing: if it is still playing when the buzzer is unmuted,
+    /// it becomes audible for the rest of its duration.
+    ///
+    /// Muting is independent of `set_volume()`. The volume is kept while
+    /// muted and applies again once the buzzer is unmuted.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The buzzer was muted or unmuted.
+    /// - `FAIL`: The sound currently playing could not be silenced or
+    ///   resumed.
+    /// - `NOSUPPORT`: The buzzer cannot be muted. This is what the default
+    ///   implementation returns.
+    fn set_muted(&self, _muted: bool) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    /// Return the lowest and highest frequency in hertz, inclusive, that the
+    /// buzzer can play. Callers can use this to clamp or reject frequencies
+    /// before calling `buzz()`, which fails for frequencies outside of it.
+    ///
+    /// The default implementation returns `(1, usize::MAX)`, i.e. no known
+    /// limits.
+    fn frequency_range(&self) -> (usize, usize) {
+        (1, usize::MAX)
+    }
+
     /// Stop the sound currently playing.
     /// After the buzzer is successfully stopped, the `buzzer_done()`
     /// callback is called.
@@ -38,7 +527,79 @@ pub trait Buzzer<'a> {
     /// - `OFF`: The buzzer wasn't playing a sound when the stop command was called.
     fn stop(&self) -> Result<(), ErrorCode>;
 
+    /// Stop the sound currently playing like `stop()`, but first fade it out
+    /// by linearly lowering its volume to zero over `ms` milliseconds. This
+    /// avoids the audible click that stopping abruptly causes on some
+    /// elements, such as piezo buzzers. The `buzzer_done()` callback is called
+    /// once the sound has stopped.
+    ///
+    /// Implementations that cannot vary the volume stop immediately, which is
+    /// what the default implementation does.
+    ///
+    /// Return values are the same as for `stop()`.
+    fn stop_with_rampdown(&self, _ms: usize) -> Result<(), ErrorCode> {
+        self.stop()
+    }
+
+    /// Play a short diagnostic tone, e.g. during board bring-up to confirm
+    /// the buzzer is wired correctly. Implementations that can read back the
+    /// state of their output also check that it is actually toggling. The
+    /// `buzzer_done()` callback is called once the tone has finished.
+    ///
+    /// The default implementation plays `SELF_TEST_FREQUENCY_HZ` for
+    /// `SELF_TEST_DURATION_MS` without any further checks.
+    ///
+    /// Return values:
+    ///
+    /// - `Ok(())`: The tone is playing, and the output toggled if that can be
+    ///   checked.
+    /// - `FAIL`: The tone could not be played, or the output does not toggle.
+    fn self_test(&self) -> Result<(), ErrorCode> {
+        self.buzz(SELF_TEST_FREQUENCY_HZ, SELF_TEST_DURATION_MS)
+    }
+
     /// Set the client to be used for callbacks of the Buzzer
     /// implementation.
     fn set_client(&self, client: &'a dyn BuzzerClient);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtttl_notes() {
+        let mut notes = RtttlNotes::new("test:d=8,o=5,b=120:c,4d#6.,p, 32h7").unwrap();
+        assert_eq!(notes.next_note(), Some(Ok((523, 250))));
+        assert_eq!(notes.next_note(), Some(Ok((1244, 750))));
+        assert_eq!(notes.next_note(), Some(Ok((0, 250))));
+        assert_eq!(notes.next_note(), Some(Ok((3952, 62))));
+        assert_eq!(notes.next_note(), None);
+    }
+
+    #[test]
+    fn test_rtttl_defaults() {
+        let mut notes = RtttlNotes::new("::a").unwrap();
+        assert_eq!(notes.next_note(), Some(Ok((1760, 952))));
+        assert_eq!(notes.next_note(), None);
+    }
+
+    #[test]
+    fn test_rtttl_malformed() {
+        assert!(RtttlNotes::new("no sections").is_err());
+        assert!(RtttlNotes::new("x:d=3:c").is_err());
+        assert!(RtttlNotes::new("x:o=9:c").is_err());
+        assert!(RtttlNotes::new("x:b=0:c").is_err());
+        assert!(RtttlNotes::new("x:q=1:c").is_err());
+        assert!(RtttlNotes::new("x:b=99999999999999999999:c").is_err());
+
+        let mut notes = RtttlNotes::new("x::x,4,c9,c5..,99999999999999999999c,c,,é").unwrap();
+        for _ in 0..5 {
+            assert_eq!(notes.next_note(), Some(Err(ErrorCode::INVAL)));
+        }
+        assert!(matches!(notes.next_note(), Some(Ok(_))));
+        assert_eq!(notes.next_note(), Some(Err(ErrorCode::INVAL)));
+        assert_eq!(notes.next_note(), Some(Err(ErrorCode::INVAL)));
+        assert_eq!(notes.next_note(), None);
+    }
+}
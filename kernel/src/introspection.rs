@@ -17,9 +17,12 @@
 use core::cell::Cell;
 
 use crate::capabilities::ProcessManagementCapability;
+use crate::errorcode::ErrorCode;
 use crate::kernel::Kernel;
+use crate::platform::platform::{self, SyscallFilter};
 use crate::process;
 use crate::process::ProcessId;
+use crate::syscall::Syscall;
 use crate::utilities::cells::NumericCellExt;
 
 /// This struct provides the inspection functions.
@@ -147,6 +150,28 @@ impl KernelInfo {
         (used, number_of_grants)
     }
 
+    /// Check whether `filter` would allow the app to make `syscall`, without
+    /// making it. This applies the same checks the kernel applies before
+    /// handling a system call, e.g. so that an introspection driver can tell
+    /// a launcher app which features the policy blocks. Pass the board's
+    /// `KernelResources::syscall_filter()` as `filter`, and give driver
+    /// numbers after `SyscallDriverLookup::translate_driver_num`.
+    ///
+    /// Returns the `ErrorCode` the app would get if the system call is
+    /// filtered, and `INVAL` if `app` is not a valid process.
+    pub fn syscall_allowed(
+        &self,
+        app: ProcessId,
+        filter: &dyn SyscallFilter,
+        syscall: &Syscall,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> Result<(), ErrorCode> {
+        self.kernel
+            .process_map_or(Err(ErrorCode::INVAL), app, |process| {
+                platform::check_syscall_filter(filter, process, syscall)
+            })
+    }
+
     /// Returns the total number of times all processes have exceeded
     /// their timeslices.
     pub fn timeslice_expirations(&self, _capability: &dyn ProcessManagementCapability) -> usize {
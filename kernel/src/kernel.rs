@@ -21,10 +21,10 @@ use crate::ipc;
 use crate::memop;
 use crate::platform::chip::Chip;
 use crate::platform::mpu::MPU;
-use crate::platform::platform::ContextSwitchCallback;
 use crate::platform::platform::KernelResources;
+use crate::platform::platform::{self, ContextSwitchCallback, IdleAction};
 use crate::platform::platform::{ProcessFault, SyscallDriverLookup, SyscallFilter};
-use crate::platform::scheduler_timer::SchedulerTimer;
+use crate::platform::scheduler_timer::{Accounting, SchedulerTimer};
 use crate::platform::watchdog::WatchDog;
 use crate::process::{self, ProcessId, Task};
 use crate::scheduler::{Scheduler, SchedulingDecision};
@@ -59,6 +59,10 @@ pub struct Kernel {
     /// created and the data structures for grants have already been
     /// established.
     grants_finalized: Cell<bool>,
+
+    /// All process identifiers below this one have been reported to
+    /// `SyscallFilter::process_started()`.
+    process_identifier_reported: Cell<usize>,
 }
 
 /// Represents the different outcomes when trying to allocate a grant region
@@ -93,6 +97,7 @@ impl Kernel {
             process_identifier_max: Cell::new(0),
             grant_counter: Cell::new(0),
             grants_finalized: Cell::new(false),
+            process_identifier_reported: Cell::new(0),
         }
     }
 
@@ -369,6 +374,7 @@ impl Kernel {
         let scheduler = resources.scheduler();
 
         resources.watchdog().tickle();
+        self.report_started_processes(resources);
         unsafe {
             // Ask the scheduler if we should do tasks inside of the kernel,
             // such as handle interrupts. A scheduler may want to prioritize
@@ -394,8 +400,9 @@ impl Kernel {
                             // For testing, it may be helpful to
                             // disable sleeping the chip in case
                             // the running test does not generate
-                            // any interrupts.
-                            if !no_sleep {
+                            // any interrupts. The board may also
+                            // have idle work to do instead.
+                            if !no_sleep && resources.idle() == IdleAction::Sleep {
                                 chip.atomic(|| {
                                     // Cannot sleep if interrupts are pending,
                                     // as on most platforms unhandled interrupts
@@ -406,7 +413,9 @@ impl Kernel {
                                     // starts, the interrupt will not be
                                     // serviced and the chip will never wake
                                     // from sleep.
-                                    if !chip.has_pending_interrupts() && !DeferredCall::has_tasks()
+                                    if !chip.has_pending_interrupts()
+                                        && !DeferredCall::has_tasks()
+                                        && resources.can_sleep()
                                     {
                                         resources.watchdog().suspend();
                                         chip.sleep();
@@ -421,6 +430,44 @@ impl Kernel {
         }
     }
 
+    /// Call `SyscallFilter::process_started()` for every process instance
+    /// created since the last call. Every time a process is loaded, started or
+    /// restarted it gets a new, larger identifier, so it is enough to look for
+    /// identifiers that have not been reported yet.
+    fn report_started_processes<KR: KernelResources<C>, C: Chip>(&self, resources: &KR) {
+        let max = self.process_identifier_max.get();
+        let reported = self.process_identifier_reported.replace(max);
+        if reported == max {
+            return;
+        }
+
+        for process in self.processes.iter().flatten() {
+            let new = (reported..max).contains(&process.processid().id());
+            let runnable = !matches!(
+                process.get_state(),
+                process::State::Terminated | process::State::Faulted
+            );
+            if new && runnable {
+                resources.syscall_filter().process_started(*process);
+            }
+        }
+    }
+
+    /// Put `process` in the fault state after it faulted while the kernel was
+    /// running it, taking the action the board's `ProcessFault` chooses, if
+    /// any, and otherwise the process's own fault policy.
+    fn fault_process<KR: KernelResources<C>, C: Chip>(
+        &self,
+        resources: &KR,
+        process: &dyn process::Process,
+    ) {
+        resources.syscall_filter().process_stopped(process);
+        match resources.process_fault().process_fault_policy(process) {
+            Some(action) => process.set_fault_state_with_action(action),
+            None => process.set_fault_state(),
+        }
+    }
+
     /// Main loop of the OS.
     ///
     /// Most of the behavior of this loop is controlled by the [`Scheduler`]
@@ -550,6 +597,9 @@ impl Kernel {
                         .context_switch_hook(process);
                     process.setup_mpu();
                     chip.mpu().enable_app_mpu();
+                    if scheduler_timer.accounting() == Accounting::ExcludeInterrupts {
+                        scheduler_timer.resume();
+                    }
                     scheduler_timer.arm();
                     let context_switch_reason = process.switch_to();
                     scheduler_timer.disarm();
@@ -567,7 +617,7 @@ impl Kernel {
                                 .is_err()
                             {
                                 // Let process deal with it as appropriate.
-                                process.set_fault_state();
+                                self.fault_process(resources, process);
                             }
                         }
                         Some(ContextSwitchReason::SyscallFired { syscall }) => {
@@ -580,6 +630,12 @@ impl Kernel {
                                 return_reason = process::StoppedExecutingReason::TimesliceExpired;
                                 break;
                             }
+                            // Do not charge the kernel's handling of the
+                            // interrupt to the process if so configured. The
+                            // timer resumes when the process runs again.
+                            if scheduler_timer.accounting() == Accounting::ExcludeInterrupts {
+                                scheduler_timer.pause();
+                            }
                             // Go to the beginning of loop to determine whether
                             // to break to handle the interrupt, continue
                             // executing this process, or switch to another
@@ -590,7 +646,7 @@ impl Kernel {
                             // Something went wrong when switching to this
                             // process. Indicate this by putting it in a fault
                             // state.
-                            process.set_fault_state();
+                            self.fault_process(resources, process);
                         }
                     }
                 }
@@ -742,6 +798,21 @@ impl Kernel {
         // Hook for process debugging.
         process.debug_syscall_called(syscall);
 
+        // Apply the platform's driver number mapping once, up front, so that
+        // filtering and dispatching all see the same driver.
+        let syscall = syscall.map_driver_number(|driver_num| {
+            resources
+                .syscall_driver_lookup()
+                .translate_driver_num(driver_num)
+        });
+
+        // Report the outcome of the syscall to the platform's audit hook.
+        let record = |result: Result<(), ErrorCode>| {
+            resources
+                .syscall_filter()
+                .record_syscall(process, &syscall, &result);
+        };
+
         // Enforce platform-specific syscall filtering here.
         //
         // Before continuing to handle non-yield syscalls the kernel first
@@ -758,7 +829,11 @@ impl Kernel {
                 which: _,
                 param_a: _,
                 param_b: _,
-            } => {} // Yield is not filterable.
+            } => {
+                // Yield is not filterable, but the platform may still want to
+                // know about it.
+                resources.syscall_filter().on_yield(process);
+            }
             Syscall::Exit {
                 which: _,
                 completion_code: _,
@@ -769,9 +844,13 @@ impl Kernel {
             } => {} // Memop is not filterable.
             _ => {
                 // Check all other syscalls for filtering.
-                if let Err(response) = resources.syscall_filter().filter_syscall(process, &syscall)
+                let permissions = resources.driver_permissions(process);
+                if let Err(response) =
+                    platform::check_syscall_filter(resources.syscall_filter(), process, &syscall)
+                        .and_then(|()| platform::check_driver_permissions(permissions, &syscall))
                 {
                     process.set_syscall_return_value(SyscallReturn::Failure(response));
+                    record(Err(response));
 
                     if config::CONFIG.trace_syscalls {
                         debug!(
@@ -801,6 +880,7 @@ impl Kernel {
                     );
                 }
                 process.set_syscall_return_value(rval);
+                record(rval.result());
             }
             Syscall::Yield {
                 which,
@@ -810,6 +890,7 @@ impl Kernel {
                 if config::CONFIG.trace_syscalls {
                     debug!("[{:?}] yield. which: {}", process.processid(), which);
                 }
+                record(Ok(()));
                 match which.try_into() {
                     Ok(YieldCall::NoWait) => {
                         // If this is a `Yield-WaitFor` AND there are no pending
@@ -863,7 +944,16 @@ impl Kernel {
             | Syscall::ReadOnlyAllow { driver_number, .. } => {
                 resources
                 .syscall_driver_lookup()
-                .with_driver(driver_number, |driver| match syscall {
+                .with_driver(driver_number, |driver| {
+                // Let the platform know an app tried to use a driver that is
+                // not provided before the error is returned to the app.
+                if driver.is_none() {
+                    resources
+                        .syscall_driver_lookup()
+                        .unrecognized_driver(process, driver_number);
+                }
+
+                match syscall {
                     Syscall::Subscribe {
                         driver_number,
                         subdriver_number,
@@ -1033,6 +1123,7 @@ impl Kernel {
                         }
 
                         process.set_syscall_return_value(rval);
+                        record(rval.result());
                     }
                     Syscall::Command {
                         driver_number,
@@ -1059,6 +1150,7 @@ impl Kernel {
                             );
                         }
                         process.set_syscall_return_value(res);
+                        record(res.result());
                     }
                     Syscall::ReadWriteAllow {
                         driver_number,
@@ -1184,6 +1276,7 @@ impl Kernel {
                             );
                         }
                         process.set_syscall_return_value(res);
+                        record(res.result());
                     }
                     Syscall::UserspaceReadableAllow {
                         driver_number,
@@ -1264,6 +1357,7 @@ impl Kernel {
                             );
                         }
                         process.set_syscall_return_value(res);
+                        record(res.result());
                     }
                     Syscall::ReadOnlyAllow {
                         driver_number,
@@ -1390,6 +1484,7 @@ impl Kernel {
                         }
 
                         process.set_syscall_return_value(res);
+                        record(res.result());
                     }
                     Syscall::Yield { .. }
                     | Syscall::Exit { .. }
@@ -1398,14 +1493,23 @@ impl Kernel {
                         // match statement:
                         debug_assert!(false, "Kernel system call handling invariant violated!");
                     },
+                }
                 })
             }
             Syscall::Exit {
                 which,
                 completion_code,
             } => {
-                // exit try restart modifies the ID of the process.
+                // exit try restart modifies the ID of the process, so record
+                // the syscall while the process still has its old ID.
                 let old_process_id = process.processid();
+                record(match which {
+                    0 | 1 => Ok(()),
+                    _ => Err(ErrorCode::NOSUPPORT),
+                });
+                if which == 0 || which == 1 {
+                    resources.syscall_filter().process_stopped(process);
+                }
                 let optional_return_value = match which {
                     // The process called the `exit-terminate` system call.
                     0 => {
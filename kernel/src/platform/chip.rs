@@ -6,6 +6,8 @@
 
 use crate::platform::mpu;
 use crate::syscall;
+use crate::ErrorCode;
+use core::cell::Cell;
 use core::fmt::Write;
 
 /// Interface for individual MCUs.
@@ -37,6 +39,26 @@ pub trait Chip {
     /// Ask the chip to check if there are any pending interrupts.
     fn has_pending_interrupts(&self) -> bool;
 
+    /// Fill `buf` with the numbers of the interrupts that are currently
+    /// pending, in ascending order, and return how many were written. If more
+    /// interrupts are pending than fit in `buf`, only the lowest numbered ones
+    /// are written.
+    ///
+    /// This is intended for debugging and profiling tools that want to sample
+    /// which interrupts fire most often. The default implementation reports no
+    /// pending interrupts.
+    fn pending_interrupts(&self, _buf: &mut [u32]) -> usize {
+        0
+    }
+
+    /// Return which interrupts are pending and which are active, i.e. being
+    /// serviced, right now. Unlike `print_state()` this is structured, so a
+    /// panic handler can stash it, e.g. in retained RAM, for a host tool to
+    /// decode. The default implementation returns an empty snapshot.
+    fn interrupt_snapshot(&self) -> InterruptSnapshot {
+        InterruptSnapshot::default()
+    }
+
     /// Returns a reference to the implementation for the MPU on this chip.
     fn mpu(&self) -> &Self::MPU;
 
@@ -50,13 +72,174 @@ pub trait Chip {
     /// chip and resumes the scheduler.
     fn sleep(&self);
 
+    /// Enter the deepest low power state the chip supports that still retains
+    /// RAM and register state, so that execution resumes after this call once
+    /// the chip wakes up.
+    ///
+    /// Unlike `sleep()`, which the scheduler uses whenever it is idle, this is
+    /// intended to be explicitly requested by a board, for example to conserve
+    /// energy on a low battery event after flushing any state. Which sources
+    /// can wake the chip from this state is chip specific. The default
+    /// implementation calls `sleep()`.
+    fn deep_sleep(&self) {
+        self.sleep();
+    }
+
+    /// Return what woke the chip from the most recent `sleep()` or
+    /// `deep_sleep()`, if the chip can tell.
+    ///
+    /// This is useful for power debugging, e.g. to attribute wakeups to their
+    /// causes. The default implementation returns `None`.
+    fn last_wakeup_source(&self) -> Option<WakeupSource> {
+        None
+    }
+
+    /// Return why the chip last reset, e.g. so a board can log whether it
+    /// restarted because of the watchdog or a power glitch.
+    ///
+    /// Chips read their reset cause register once at boot and report that
+    /// value for the lifetime of the kernel. The default implementation
+    /// returns `ResetReason::Unknown`.
+    fn reset_reason(&self) -> ResetReason {
+        ResetReason::Unknown
+    }
+
+    /// Copy the chip's factory-programmed unique identifier, such as a serial
+    /// number, into `buf` and return the number of bytes written. If `buf` is
+    /// shorter than the identifier only its first bytes are written.
+    ///
+    /// This is useful for device attestation or provisioning without
+    /// chip-specific code. The default implementation writes nothing and
+    /// returns 0, for chips without a unique identifier.
+    fn device_id(&self, _buf: &mut [u8]) -> usize {
+        0
+    }
+
+    /// Return the temperature of the chip's core in degrees Celsius, read from
+    /// an internal sensor, or `None` if the chip has no such sensor or it
+    /// cannot be read right now.
+    ///
+    /// This lets portable code, e.g. a governor capsule, throttle work when
+    /// the chip gets hot. The reading must be quick and must not interfere
+    /// with peripherals used by drivers. The default implementation returns
+    /// `None`.
+    fn core_temperature_c(&self) -> Option<i16> {
+        None
+    }
+
+    /// Return the chip's supply voltage in millivolts, read from an internal
+    /// sensor, or `None` if the chip has no such sensor or it cannot be read
+    /// right now. As with `core_temperature_c()`, the reading must be quick.
+    /// The default implementation returns `None`.
+    fn supply_voltage_mv(&self) -> Option<u16> {
+        None
+    }
+
+    /// Capture the chip's current clock configuration, so that it can later
+    /// be brought back with `restore_clocks()`.
+    ///
+    /// This lets e.g. a power governor drop to a low-frequency clock while
+    /// idle and return to the high-performance configuration on activity.
+    /// What is captured is chip specific. The default implementation returns
+    /// an empty `ClockConfig`.
+    fn save_clocks(&self) -> ClockConfig {
+        ClockConfig::default()
+    }
+
+    /// Return the clocks to a configuration captured by `save_clocks()`.
+    ///
+    /// Peripheral drivers are not told about the change, so callers should
+    /// only restore a configuration whose peripheral clocks and frequencies
+    /// are still what active drivers expect.
+    ///
+    /// Returns `INVAL` if `config` was not captured on this chip, `OFF` if
+    /// the chip has not been set up to change its clocks, and the default
+    /// implementation returns `NOSUPPORT`.
+    fn restore_clocks(&self, _config: &ClockConfig) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
+    /// Return how long, in microseconds, the most recent call to
+    /// `service_pending_interrupts()` that serviced at least one interrupt
+    /// took, if the chip measures it.
+    ///
+    /// This helps establish real-time bounds, e.g. through a profiling
+    /// capsule. Chips that measure it may only do so once profiling has been
+    /// enabled in a chip-specific way, as it can cost power. The default
+    /// implementation returns `None`.
+    fn last_service_duration_us(&self) -> Option<u32> {
+        None
+    }
+
+    /// Like `last_service_duration_us()`, but return the longest duration
+    /// measured so far. The default implementation returns `None`.
+    fn max_service_duration_us(&self) -> Option<u32> {
+        None
+    }
+
+    /// Return the number of CPU cycles counted so far, for benchmarking
+    /// kernel paths.
+    ///
+    /// Only differences between two readings are meaningful. The counter is
+    /// 64 bits wide, but chips whose hardware counter is narrower may extend
+    /// it in software, in which case it is only monotonic if read at least
+    /// once per wrap of the hardware counter. Most cores stop counting while
+    /// asleep, so intervals spanning a `sleep()` are not wall-clock time.
+    /// Chips may require the counter to be enabled in a chip-specific way, as
+    /// it can cost power. The default implementation returns 0.
+    fn cycle_count(&self) -> u64 {
+        0
+    }
+
+    /// Mask interrupt `irq`, so that it is neither delivered nor serviced
+    /// until `unmask_interrupt()` is called with the same number. The
+    /// peripheral raising it keeps running.
+    ///
+    /// This is intended for diagnostics, e.g. to quiet a peripheral that
+    /// raises spurious interrupts from a debug capsule. Masking an interrupt
+    /// stalls the driver that relies on it, so normal operation should not
+    /// be expected while it is masked. The default implementation does
+    /// nothing.
+    fn mask_interrupt(&self, _irq: u32) {}
+
+    /// Undo `mask_interrupt()` for interrupt `irq`. An interrupt that was
+    /// raised while masked is serviced once unmasked. The default
+    /// implementation does nothing.
+    fn unmask_interrupt(&self, _irq: u32) {}
+
+    /// Complete all memory writes the CPU has issued, including any held in
+    /// caches or write buffers, before returning. Drivers call this before
+    /// handing a buffer the CPU just wrote to a DMA engine or other bus
+    /// master, so the hardware does not read stale data.
+    ///
+    /// Chips with data caches must also clean them here. The default
+    /// implementation issues a sequentially consistent memory fence, which
+    /// orders the writes but does not wait for them to complete.
+    fn data_barrier(&self) {
+        core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+    }
+
     /// Run a function in an atomic state, which means that interrupts are
     /// disabled so that an interrupt will not fire during the passed in
     /// function's execution.
+    ///
+    /// Calls may be nested. Implementations must restore the interrupt state
+    /// from before the call when `f` returns, rather than unconditionally
+    /// enabling interrupts, so that an inner call does not end the critical
+    /// section of an outer one.
     unsafe fn atomic<F, R>(&self, f: F) -> R
     where
         F: FnOnce() -> R;
 
+    /// Disable interrupts until the returned token is dropped.
+    ///
+    /// This is the same critical section as `atomic()`, held as a guard
+    /// instead of around a closure, for critical sections that do not fit in
+    /// a single lexical scope. The token saves the interrupt state from before
+    /// the call, so critical sections nest like `atomic()` calls do, as long
+    /// as tokens are dropped in the reverse order they were created.
+    unsafe fn enter_critical(&self) -> CriticalToken;
+
     /// Print out chip state (system registers) to a supplied
     /// writer. This does not print out the execution context
     /// (data registers), as this depends on how they are stored;
@@ -68,6 +251,148 @@ pub trait Chip {
     /// the Display trait.
     /// Used by panic.
     unsafe fn print_state(&self, writer: &mut dyn Write);
+
+    /// Print out chip state like `print_state()`, with the amount of detail
+    /// selected by `level`. This lets a panic handler keep its output short,
+    /// e.g. on a slow UART or when panics repeat.
+    ///
+    /// `StateDumpLevel::Normal` prints the same state as `print_state()`.
+    /// Chips that do not distinguish between levels print that state at every
+    /// level, which is what the default implementation does.
+    unsafe fn print_state_at_level(&self, writer: &mut dyn Write, _level: StateDumpLevel) {
+        self.print_state(writer);
+    }
+}
+
+/// The class of interrupt that woke a chip, as reported by
+/// `Chip::last_wakeup_source()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WakeupSource {
+    /// A timer, alarm, or watchdog.
+    Timer,
+    /// A GPIO pin or external interrupt line.
+    Gpio,
+    /// A UART.
+    Uart,
+    /// An I2C controller or target.
+    I2c,
+    /// An SPI controller or target.
+    Spi,
+    /// A USB controller.
+    Usb,
+    /// A DMA channel.
+    Dma,
+    /// Any other interrupt, with its chip-specific interrupt number.
+    Other(u32),
+}
+
+/// A critical section started by `Chip::enter_critical()`.
+///
+/// Interrupts stay disabled while the token exists. Dropping it restores the
+/// interrupt state from before the critical section started.
+#[must_use = "the critical section ends when the token is dropped"]
+pub struct CriticalToken {
+    state: usize,
+    restore: unsafe fn(usize),
+}
+
+impl CriticalToken {
+    /// Create a token for a critical section that ends by passing `state` to
+    /// `restore`.
+    ///
+    /// ## Safety
+    ///
+    /// Interrupts must have just been disabled, with `state` recording
+    /// whether they were enabled before so that `restore` only re-enables
+    /// them if they were.
+    pub unsafe fn new(state: usize, restore: unsafe fn(usize)) -> Self {
+        Self { state, restore }
+    }
+}
+
+impl Drop for CriticalToken {
+    fn drop(&mut self) {
+        unsafe { (self.restore)(self.state) }
+    }
+}
+
+/// The cause of the last chip reset, as reported by `Chip::reset_reason()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResetReason {
+    /// The chip cannot determine the cause.
+    Unknown,
+    /// The supply was switched on.
+    PowerOn,
+    /// A supply voltage dropped below the brown-out threshold.
+    Brownout,
+    /// The watchdog expired.
+    Watchdog,
+    /// The external reset pin was asserted.
+    ExternalPin,
+    /// Software requested a reset, e.g. through the CPU's reset request bit.
+    Software,
+    /// The chip woke from a sleep mode that is left through a reset.
+    Wakeup,
+}
+
+/// Number of 32-bit words in each bitmap of an `InterruptSnapshot`.
+pub const INTERRUPT_SNAPSHOT_WORDS: usize = 8;
+
+/// The state of the chip's interrupts, as returned by
+/// `Chip::interrupt_snapshot()`.
+///
+/// Bit `n % 32` of word `n / 32` describes interrupt number `n`. The layout
+/// is fixed so the snapshot can be copied out as raw words.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct InterruptSnapshot {
+    /// Interrupts that are waiting to be serviced.
+    pub pending: [u32; INTERRUPT_SNAPSHOT_WORDS],
+    /// Interrupts whose handlers are running, including preempted ones.
+    pub active: [u32; INTERRUPT_SNAPSHOT_WORDS],
+}
+
+impl InterruptSnapshot {
+    fn bit(words: &[u32; INTERRUPT_SNAPSHOT_WORDS], irq: u32) -> bool {
+        words
+            .get(irq as usize / 32)
+            .is_some_and(|word| word & (1 << (irq % 32)) != 0)
+    }
+
+    /// Whether interrupt `irq` was pending.
+    pub fn is_pending(&self, irq: u32) -> bool {
+        Self::bit(&self.pending, irq)
+    }
+
+    /// Whether interrupt `irq` was active.
+    pub fn is_active(&self, irq: u32) -> bool {
+        Self::bit(&self.active, irq)
+    }
+}
+
+/// Number of 32-bit words in a `ClockConfig`.
+pub const CLOCK_CONFIG_WORDS: usize = 8;
+
+/// A snapshot of the chip's clock configuration, as returned by
+/// `Chip::save_clocks()`.
+///
+/// The meaning of the words is chip specific, so a snapshot can only be
+/// restored on the chip that captured it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct ClockConfig {
+    pub words: [u32; CLOCK_CONFIG_WORDS],
+}
+
+/// How much chip state `Chip::print_state_at_level()` prints.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StateDumpLevel {
+    /// Only the essential fault status.
+    Minimal,
+    /// The state printed by `Chip::print_state()`.
+    Normal,
+    /// Everything the chip can provide that may help debugging.
+    Verbose,
 }
 
 /// Interface for handling interrupts on a hardware chip.
@@ -117,18 +442,186 @@ pub trait ClockInterface {
     fn is_enabled(&self) -> bool;
     fn enable(&self);
     fn disable(&self);
+
+    /// Return the frequency of this clock in Hz.
+    ///
+    /// Drivers can use this to derive baud rates, timer periods, and other
+    /// clock divisors without reaching into chip-specific clock management
+    /// code. Implementations that cannot determine the frequency of the clock
+    /// return `0`, which is also the default.
+    fn frequency(&self) -> u32 {
+        0
+    }
+
+    /// Return the clock this clock is derived from, e.g. the bus clock a
+    /// peripheral clock is gated from, if it must also be enabled for this
+    /// clock to run.
+    ///
+    /// This lets generic code enable a clock along with everything it depends
+    /// on, see `enable_with_parents()`. The default returns `None`.
+    fn parent(&self) -> Option<&'static dyn ClockInterface> {
+        None
+    }
+}
+
+/// Enable `clock` after enabling each of its ancestors, starting from the root
+/// of the chain returned by `ClockInterface::parent()`.
+pub fn enable_with_parents(clock: &dyn ClockInterface) {
+    if let Some(parent) = clock.parent() {
+        enable_with_parents(parent);
+    }
+    clock.enable();
 }
 
 /// Helper struct for interfaces that expect clocks, but have no clock control.
 pub struct NoClockControl {}
+
+impl NoClockControl {
+    /// The frequency reported for clocks with no clock control. Since nothing
+    /// is known about the underlying clock, this is the "unknown" value of
+    /// `ClockInterface::frequency()`.
+    pub const FREQUENCY_HZ: u32 = 0;
+}
+
 impl ClockInterface for NoClockControl {
     fn is_enabled(&self) -> bool {
         true
     }
     fn enable(&self) {}
     fn disable(&self) {}
+    fn frequency(&self) -> u32 {
+        Self::FREQUENCY_HZ
+    }
+    fn parent(&self) -> Option<&'static dyn ClockInterface> {
+        None
+    }
 }
 
 /// Instance of NoClockControl for things that need references to
 /// `ClockInterface` objects.
 pub const NO_CLOCK_CONTROL: NoClockControl = NoClockControl {};
+
+/// A clock shared by multiple drivers.
+///
+/// Each call to `enable()` must be balanced by a call to `disable()`. The
+/// underlying clock is enabled by the first `enable()` and only disabled once
+/// every driver that enabled it has disabled it again, so one driver disabling
+/// the clock does not stop the peripherals of the others. Unbalanced calls to
+/// `disable()` are ignored.
+///
+/// The kernel is single threaded, so the enable count does not need to be
+/// atomic.
+pub struct RefCountedClock<C: ClockInterface> {
+    clock: C,
+    count: Cell<usize>,
+}
+
+impl<C: ClockInterface> RefCountedClock<C> {
+    pub const fn new(clock: C) -> Self {
+        Self {
+            clock,
+            count: Cell::new(0),
+        }
+    }
+
+    /// Number of outstanding `enable()` calls.
+    pub fn enable_count(&self) -> usize {
+        self.count.get()
+    }
+}
+
+impl<C: ClockInterface> ClockInterface for RefCountedClock<C> {
+    fn is_enabled(&self) -> bool {
+        self.clock.is_enabled()
+    }
+
+    fn enable(&self) {
+        if self.count.get() == 0 {
+            self.clock.enable();
+        }
+        self.count.set(self.count.get() + 1);
+    }
+
+    fn disable(&self) {
+        match self.count.get() {
+            0 => {}
+            1 => {
+                self.count.set(0);
+                self.clock.disable();
+            }
+            count => self.count.set(count - 1),
+        }
+    }
+
+    fn frequency(&self) -> u32 {
+        self.clock.frequency()
+    }
+
+    fn parent(&self) -> Option<&'static dyn ClockInterface> {
+        self.clock.parent()
+    }
+}
+
+/// A clock wrapper that can be told to misbehave, for testing how drivers
+/// handle clock failures.
+///
+/// By default it forwards everything to the wrapped clock. With
+/// `set_enable_fails(true)`, `enable()` no longer reaches the wrapped clock,
+/// like a clock that won't start. With `set_reported_enabled()`,
+/// `is_enabled()` returns a fixed value regardless of the wrapped clock. Both
+/// together simulate a clock that reports enabled but is actually stopped.
+///
+/// Like `NoClockControl`, this is intended for tests and should not be used
+/// on a production board.
+pub struct FaultyClock<C: ClockInterface> {
+    clock: C,
+    enable_fails: Cell<bool>,
+    reported_enabled: Cell<Option<bool>>,
+}
+
+impl<C: ClockInterface> FaultyClock<C> {
+    pub const fn new(clock: C) -> Self {
+        Self {
+            clock,
+            enable_fails: Cell::new(false),
+            reported_enabled: Cell::new(None),
+        }
+    }
+
+    /// Make subsequent calls to `enable()` silently do nothing.
+    pub fn set_enable_fails(&self, fails: bool) {
+        self.enable_fails.set(fails);
+    }
+
+    /// Make `is_enabled()` return `enabled`, or the state of the wrapped clock
+    /// if `None`.
+    pub fn set_reported_enabled(&self, enabled: Option<bool>) {
+        self.reported_enabled.set(enabled);
+    }
+}
+
+impl<C: ClockInterface> ClockInterface for FaultyClock<C> {
+    fn is_enabled(&self) -> bool {
+        self.reported_enabled
+            .get()
+            .unwrap_or_else(|| self.clock.is_enabled())
+    }
+
+    fn enable(&self) {
+        if !self.enable_fails.get() {
+            self.clock.enable();
+        }
+    }
+
+    fn disable(&self) {
+        self.clock.disable();
+    }
+
+    fn frequency(&self) -> u32 {
+        self.clock.frequency()
+    }
+
+    fn parent(&self) -> Option<&'static dyn ClockInterface> {
+        self.clock.parent()
+    }
+}
@@ -13,9 +13,18 @@ pub mod watchdog;
 
 pub(crate) mod platform;
 
+pub use crate::assert_unique_drivers;
+
+pub use self::platform::drivers_unique;
+pub use self::platform::CachedSyscallDriverLookup;
 pub use self::platform::ContextSwitchCallback;
+pub use self::platform::DriverPermissions;
+pub use self::platform::IdleAction;
 pub use self::platform::KernelResources;
 pub use self::platform::ProcessFault;
+pub use self::platform::RateLimitFilter;
 pub use self::platform::SyscallDriverLookup;
 pub use self::platform::SyscallFilter;
+pub use self::platform::SyscallFilterChain;
 pub use self::platform::TbfHeaderFilterDefaultAllow;
+pub use self::platform::RATE_LIMIT_DENIED_REASON;
@@ -5,7 +5,9 @@
 //! Interface for configuring the Memory Protection Unit.
 
 use core::cmp;
-use core::fmt::{self, Display};
+use core::fmt::{self, Display, Write};
+
+use crate::ErrorCode;
 
 /// User mode access permissions.
 #[derive(Copy, Clone, Debug)]
@@ -53,6 +55,22 @@ impl Region {
     }
 }
 
+/// A range of memory covered by an MPU configuration, as returned by
+/// `MPU::regions()` for the hardware's current configuration and by
+/// `MPU::config_regions()` for a process's configuration.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct MpuRegionInfo {
+    /// The memory address where the range starts.
+    pub start: usize,
+
+    /// The number of bytes in the range.
+    pub size: usize,
+
+    /// User mode access permissions of the range, or `None` if user mode
+    /// cannot access it at all.
+    pub permissions: Option<Permissions>,
+}
+
 /// Null type for the default type of the `MpuConfig` type in an implementation
 /// of the `MPU` trait.
 ///
@@ -113,6 +131,67 @@ pub trait MPU {
     /// Returns the maximum number of regions supported by the MPU.
     fn number_total_regions(&self) -> usize;
 
+    /// Returns the size in bytes of the smallest region the MPU can protect.
+    /// Regions are rounded up to at least this size.
+    fn min_region_size(&self) -> usize;
+
+    /// Print which region of the hardware's current configuration
+    /// `fault_addr` falls in, along with that region's permissions, or that
+    /// no region covers it. Used by panic output to explain memory management
+    /// faults of processes.
+    ///
+    /// The default implementation prints nothing.
+    fn describe_fault(&self, _fault_addr: usize, _writer: &mut dyn Write) {}
+
+    /// Fill `out` with the ranges of memory covered by the hardware's current
+    /// configuration, in the order the MPU numbers its regions, and return how
+    /// many were written. Stops once `out` is full. Where ranges overlap, the
+    /// MPU's own priority rules decide which permissions apply. A debug
+    /// capsule can use this to show the live MPU layout.
+    ///
+    /// The default implementation writes nothing and returns 0.
+    fn regions(&self, _out: &mut [MpuRegionInfo]) -> usize {
+        0
+    }
+
+    /// Like `regions()`, but describe the regions allocated in `config`,
+    /// whether or not it is currently loaded into the hardware. The ranges
+    /// are those requested when allocating the regions, which may be smaller
+    /// than what the hardware ends up protecting because of its alignment
+    /// rules. This shows how much memory a process can actually access, e.g.
+    /// to right-size its memory requests.
+    ///
+    /// The default implementation writes nothing and returns 0.
+    fn config_regions(&self, _config: &Self::MpuConfig, _out: &mut [MpuRegionInfo]) -> usize {
+        0
+    }
+
+    /// Mark hardware region `region_id` as immutable until the next reset.
+    ///
+    /// Once locked, the region keeps whatever the hardware currently holds:
+    /// `allocate_region` will no longer hand it out and `configure_mpu`
+    /// leaves it untouched. This lets a board pin a region set up during
+    /// boot (for example, to guard a kernel stack) against later process
+    /// configurations.
+    ///
+    /// A configuration that already holds a region in a slot that is later
+    /// locked conflicts with the lock. Such a configuration is refused rather
+    /// than applied partially: `allocate_region` returns `None` for it, and
+    /// `configure_mpu` grants the process no access to any memory instead of
+    /// applying the configuration without the locked region.
+    ///
+    /// Whether the lock is enforced in hardware depends on the chip. The
+    /// Cortex-M MPU has no lock bit, so its implementation only enforces the
+    /// lock in the kernel's own bookkeeping. Memory protection units with a
+    /// lock bit, such as the RISC-V PMP `L` bit, could enforce it in
+    /// hardware as well.
+    ///
+    /// Returns `INVAL` if `region_id` does not name a region that can be
+    /// locked. The default implementation returns `NOSUPPORT`.
+    fn lock_region(&self, _region_id: usize) -> Result<(), ErrorCode> {
+        Err(ErrorCode::NOSUPPORT)
+    }
+
     /// Creates a new empty MPU configuration.
     ///
     /// The returned configuration must not have any userspace-accessible
@@ -275,6 +354,10 @@ impl MPU for () {
         0
     }
 
+    fn min_region_size(&self) -> usize {
+        0
+    }
+
     fn new_config(&self) -> Option<MpuConfigDefault> {
         Some(MpuConfigDefault)
     }
@@ -4,7 +4,10 @@
 
 //! Interfaces for implementing boards in Tock.
 
+use core::cell::Cell;
+
 use crate::errorcode;
+use crate::hil::time::{ConvertTicks, Ticks, Time};
 use crate::platform::chip::Chip;
 use crate::platform::scheduler_timer;
 use crate::platform::watchdog;
@@ -12,6 +15,7 @@ use crate::process;
 use crate::scheduler::Scheduler;
 use crate::syscall;
 use crate::syscall_driver::SyscallDriver;
+use crate::ProcessId;
 use tock_tbf::types::CommandPermissions;
 
 /// Combination trait that boards provide to the kernel that includes all of
@@ -73,6 +77,168 @@ pub trait KernelResources<C: Chip> {
     /// Returns a reference to the implementation of the WatchDog on this
     /// platform.
     fn watchdog(&self) -> &Self::WatchDog;
+
+    /// Called by the kernel loop when the scheduler has decided there is
+    /// nothing to do, before the chip is put to sleep. This lets a board do
+    /// its own idle work, such as blinking an LED, without modifying the chip
+    /// crate. The default implementation calls `background_work()` and
+    /// returns `IdleAction::Continue` if it did some work, and
+    /// `IdleAction::Sleep` otherwise.
+    ///
+    /// Returning `IdleAction::Continue` skips sleeping for this iteration of
+    /// the kernel loop, which then starts over. If there is still nothing to
+    /// do, this is called again right away, so a board that keeps returning
+    /// `Continue` keeps the CPU fully busy. Only return `Continue` while there
+    /// is more idle work to do, and `Sleep` once it is done.
+    fn idle(&self) -> IdleAction {
+        if self.background_work() {
+            IdleAction::Continue
+        } else {
+            IdleAction::Sleep
+        }
+    }
+
+    /// Run one step of a low-priority background task, e.g. accumulating
+    /// entropy, when no process is runnable and no interrupts or deferred
+    /// calls are pending. Return `true` if the step did useful work, so the
+    /// kernel loop runs again before sleeping, or `false` if there is nothing
+    /// to do and the chip may sleep. The default implementation returns
+    /// `false`.
+    ///
+    /// This is called by the default `idle()`, so it has no effect on boards
+    /// that implement `idle()` themselves. Each step should be short, as
+    /// interrupts are only serviced once it returns.
+    fn background_work(&self) -> bool {
+        false
+    }
+
+    /// Called right before the chip is put to sleep, with interrupts
+    /// disabled, after `idle()` returned `IdleAction::Sleep`. Returning
+    /// `false` skips sleeping and the kernel loop starts over, e.g. to keep
+    /// the CPU awake while a DMA transfer into a buffer an app will read
+    /// right away is in flight. The default implementation returns `true`.
+    ///
+    /// Unlike `idle()`, this must not do any work, only check state, as it
+    /// runs with interrupts disabled. As with `IdleAction::Continue`, a board
+    /// that keeps returning `false` prevents the chip from ever sleeping, so
+    /// only do so for as long as the condition lasts.
+    fn can_sleep(&self) -> bool {
+        true
+    }
+
+    /// Returns the drivers `process` may use. The kernel checks this for
+    /// every system call routed to a driver, after the `SyscallFilter` has
+    /// allowed it and before `SyscallDriverLookup::with_driver`, and rejects
+    /// calls to other drivers with `NODEVICE`, as if the driver did not
+    /// exist.
+    ///
+    /// This keeps a per-process driver policy in one place in the board file.
+    /// The default implementation allows every process to use every driver.
+    fn driver_permissions(&self, _process: &dyn process::Process) -> &DriverPermissions {
+        &DriverPermissions::ALLOW_ALL
+    }
+}
+
+/// The set of drivers a process may use, see
+/// `KernelResources::driver_permissions()`.
+///
+/// ## Example
+///
+/// ```ignore
+/// // Must be sorted, or this fails to compile.
+/// const SENSOR_APP_DRIVERS: DriverPermissions = DriverPermissions::new(&[
+///     capsules_core::alarm::DRIVER_NUM,
+///     capsules_core::console::DRIVER_NUM,
+///     capsules_extra::temperature::DRIVER_NUM,
+/// ]);
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct DriverPermissions {
+    /// Sorted driver numbers, or `None` to allow all drivers.
+    allowed: Option<&'static [usize]>,
+}
+
+impl DriverPermissions {
+    /// Allow every driver.
+    pub const ALLOW_ALL: DriverPermissions = DriverPermissions { allowed: None };
+
+    /// Allow exactly the drivers in `allowed`, which must be sorted in
+    /// ascending order so that `allows()` can search it.
+    ///
+    /// Panics if `allowed` is not sorted, which fails the build when this is
+    /// used to initialize a `const`.
+    pub const fn new(allowed: &'static [usize]) -> DriverPermissions {
+        let mut i = 1;
+        while i < allowed.len() {
+            assert!(
+                allowed[i - 1] < allowed[i],
+                "DriverPermissions must be sorted"
+            );
+            i += 1;
+        }
+        DriverPermissions {
+            allowed: Some(allowed),
+        }
+    }
+
+    /// Whether the driver with number `driver_num` may be used.
+    pub fn allows(&self, driver_num: usize) -> bool {
+        self.allowed
+            .is_none_or(|allowed| allowed.binary_search(&driver_num).is_ok())
+    }
+}
+
+/// Whether no driver number appears twice in `driver_nums`. This is a
+/// `const fn` for `assert_unique_drivers!`.
+pub const fn drivers_unique(driver_nums: &[usize]) -> bool {
+    let mut i = 0;
+    while i < driver_nums.len() {
+        let mut j = i + 1;
+        while j < driver_nums.len() {
+            if driver_nums[i] == driver_nums[j] {
+                return false;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Fail to compile if two of the given driver numbers are equal.
+///
+/// Two capsules sharing a driver number would otherwise silently route one
+/// capsule's system calls to the other. Boards pass the driver numbers they
+/// match on in `SyscallDriverLookup::with_driver`, either as a list of two or
+/// more numbers or as a single `&[usize]` constant.
+///
+/// ```ignore
+/// kernel::platform::assert_unique_drivers!(
+///     capsules_core::console::DRIVER_NUM,
+///     capsules_core::alarm::DRIVER_NUM,
+///     capsules_core::led::DRIVER_NUM,
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_unique_drivers {
+    ($first:expr, $($rest:expr),+ $(,)?) => {
+        $crate::assert_unique_drivers!(&[$first, $($rest),+]);
+    };
+    ($driver_nums:expr $(,)?) => {
+        const _: () = assert!(
+            $crate::platform::drivers_unique($driver_nums),
+            "two drivers share a driver number"
+        );
+    };
+}
+
+/// What the kernel loop should do when idle, see `KernelResources::idle()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IdleAction {
+    /// Put the chip to sleep until the next interrupt.
+    Sleep,
+    /// Skip sleeping and run the kernel loop again.
+    Continue,
 }
 
 /// Configure the system call dispatch mapping.
@@ -114,6 +280,115 @@ pub trait SyscallDriverLookup {
     fn with_driver<F, R>(&self, driver_num: usize, f: F) -> R
     where
         F: FnOnce(Option<&dyn SyscallDriver>) -> R;
+
+    /// Called by the kernel when `process` makes a system call to
+    /// `driver_num` and `with_driver` did not provide a driver for it, right
+    /// before `NODEVICE` is returned to the process.
+    ///
+    /// This is useful for debugging, for example to log or count attempts to
+    /// reach drivers the board does not include. The default implementation
+    /// does nothing.
+    fn unrecognized_driver(&self, _process: &dyn process::Process, _driver_num: usize) {}
+
+    /// Returns the driver numbers that `with_driver` provides a driver for.
+    ///
+    /// This lets introspection tools learn which drivers a board exposes
+    /// without probing every possible driver number. The list is maintained by
+    /// the board and must be kept in sync with `with_driver`. The default
+    /// implementation returns an empty list.
+    fn driver_nums(&self) -> &'static [usize] {
+        &[]
+    }
+
+    /// Map the driver number `requested` by a process to the driver number
+    /// the kernel uses for it.
+    ///
+    /// This lets a board run apps built for a different assignment of driver
+    /// numbers, e.g. from another board, without recompiling them. The kernel
+    /// translates the driver number of every subscribe, command, and allow
+    /// system call before filtering or dispatching it, so upcalls and allowed
+    /// buffers are always associated with the translated number. The default
+    /// implementation returns `requested` unchanged.
+    fn translate_driver_num(&self, requested: usize) -> usize {
+        requested
+    }
+
+    /// Return the driver for `driver_num`, like `with_driver`, but as a
+    /// `'static` reference that may be kept across system calls.
+    ///
+    /// This allows wrappers such as `CachedSyscallDriverLookup` to cache the
+    /// result of a lookup. A board that implements it must return the same
+    /// driver `with_driver` would. The default implementation returns `None`,
+    /// which disables caching.
+    fn static_driver(&self, _driver_num: usize) -> Option<&'static dyn SyscallDriver> {
+        None
+    }
+}
+
+/// A `SyscallDriverLookup` that caches the driver found by the last lookup.
+///
+/// Repeated system calls to the same driver, e.g. from an app polling a
+/// sensor, then skip the lookup of the wrapped implementation.
+///
+/// Only drivers returned by `SyscallDriverLookup::static_driver` are cached;
+/// other lookups fall back to `with_driver` of the wrapped implementation.
+/// Cached drivers are `'static`, so a cached entry stays valid as long as the
+/// mapping from driver numbers to drivers does not change. A board that
+/// changes the mapping at runtime must call `invalidate()`.
+pub struct CachedSyscallDriverLookup<L: SyscallDriverLookup> {
+    lookup: L,
+    last: Cell<Option<(usize, &'static dyn SyscallDriver)>>,
+}
+
+impl<L: SyscallDriverLookup> CachedSyscallDriverLookup<L> {
+    pub const fn new(lookup: L) -> Self {
+        Self {
+            lookup,
+            last: Cell::new(None),
+        }
+    }
+
+    /// Forget the cached driver.
+    pub fn invalidate(&self) {
+        self.last.set(None);
+    }
+}
+
+impl<L: SyscallDriverLookup> SyscallDriverLookup for CachedSyscallDriverLookup<L> {
+    fn with_driver<F, R>(&self, driver_num: usize, f: F) -> R
+    where
+        F: FnOnce(Option<&dyn SyscallDriver>) -> R,
+    {
+        if let Some((num, driver)) = self.last.get() {
+            if num == driver_num {
+                return f(Some(driver));
+            }
+        }
+
+        match self.lookup.static_driver(driver_num) {
+            Some(driver) => {
+                self.last.set(Some((driver_num, driver)));
+                f(Some(driver))
+            }
+            None => self.lookup.with_driver(driver_num, f),
+        }
+    }
+
+    fn unrecognized_driver(&self, process: &dyn process::Process, driver_num: usize) {
+        self.lookup.unrecognized_driver(process, driver_num)
+    }
+
+    fn driver_nums(&self) -> &'static [usize] {
+        self.lookup.driver_nums()
+    }
+
+    fn translate_driver_num(&self, requested: usize) -> usize {
+        self.lookup.translate_driver_num(requested)
+    }
+
+    fn static_driver(&self, driver_num: usize) -> Option<&'static dyn SyscallDriver> {
+        self.lookup.static_driver(driver_num)
+    }
 }
 
 /// Trait for implementing system call filters that the kernel uses to decide
@@ -134,11 +409,356 @@ pub trait SyscallFilter {
     ) -> Result<(), errorcode::ErrorCode> {
         Ok(())
     }
+
+    /// Check whether the provided process may access the driver with number
+    /// `driver_num`. This is called for every system call that is routed to a
+    /// driver (subscribe, command, and the allow variants), after
+    /// `filter_syscall` has allowed the system call and before the kernel
+    /// dispatches it through `SyscallDriverLookup::with_driver`. If access is
+    /// denied, return `Err()` with the `ErrorCode` that will be returned to the
+    /// calling application. The default implementation allows access to all
+    /// drivers.
+    ///
+    /// This lets a platform gate entire drivers (e.g. the radio) without
+    /// having to inspect the individual system call arguments.
+    fn filter_driver(
+        &self,
+        _process: &dyn process::Process,
+        _driver_num: usize,
+    ) -> Result<(), errorcode::ErrorCode> {
+        Ok(())
+    }
+
+    /// Called by the kernel after it has handled `syscall` for `process`,
+    /// with `Err()` holding the `ErrorCode` returned to the process if the
+    /// system call failed (including if it was rejected by `filter_syscall` or
+    /// `filter_driver`), and `Ok(())` otherwise. Unlike the filtering methods
+    /// this is called for every system call, including yield, exit and memop.
+    /// The default implementation does nothing.
+    ///
+    /// This allows a platform to keep an audit trail of system calls, for
+    /// example a per-process histogram or a ring buffer of recent calls for
+    /// post-mortem analysis.
+    fn record_syscall(
+        &self,
+        _process: &dyn process::Process,
+        _syscall: &syscall::Syscall,
+        _result: &Result<(), errorcode::ErrorCode>,
+    ) {
+    }
+
+    /// Called by the kernel every time `process` calls yield, before the
+    /// yield is handled. Yield cannot be filtered, so this is the only
+    /// filtering hook that sees it. The default implementation does nothing.
+    ///
+    /// This is useful for studying how cooperative processes are, for example
+    /// by counting yields per process.
+    fn on_yield(&self, _process: &dyn process::Process) {}
+
+    /// Called by the kernel when a new instance of `process` is about to run
+    /// for the first time, i.e. after it was loaded, started or restarted and
+    /// before any of its system calls are filtered. The default implementation
+    /// does nothing.
+    ///
+    /// Together with `process_stopped`, this gives a filter a place to set up
+    /// and free per-process bookkeeping, such as a rate limiting table.
+    fn process_started(&self, _process: &dyn process::Process) {}
+
+    /// Called by the kernel when it stops `process` because it exited (with
+    /// either `exit-terminate` or `exit-restart`) or faulted. This happens
+    /// after its last system call has been passed to `record_syscall`, and
+    /// before its state is freed or it is restarted, so `process` still has
+    /// the identifier it had while running. The default implementation does
+    /// nothing.
+    ///
+    /// Processes stopped from outside the kernel loop, e.g. by the process
+    /// console, are not reported, so bookkeeping set up in `process_started`
+    /// may still go stale and should tolerate that.
+    fn process_stopped(&self, _process: &dyn process::Process) {}
+
+    /// Return a filter-defined code explaining why the most recent system
+    /// call of `process` that this filter rejected was denied, e.g. to tell
+    /// a rate limit apart from a missing permission. Returns `None` if the
+    /// filter has not rejected any system call of `process` or does not keep
+    /// track of reasons, which is what the default implementation does.
+    ///
+    /// The `ErrorCode` returned to a process for a rejected system call cannot
+    /// carry this information. A board can make it available to apps
+    /// instead with the `syscall_denial` capsule, so they can query the
+    /// reason after a denial.
+    fn syscall_denied_reason(&self, _process: &dyn process::Process) -> Option<u32> {
+        None
+    }
+}
+
+/// Apply `filter` to `syscall` of `process` the way the kernel does before
+/// handling it. Yield, exit and memop cannot be filtered and are always
+/// allowed. For other system calls `filter_driver` is only consulted if
+/// `filter_syscall` allows the system call as a whole.
+///
+/// The filtering methods do not have side effects, so this can also be used
+/// to check whether a system call would be allowed without making it.
+pub(crate) fn check_syscall_filter<F: SyscallFilter + ?Sized>(
+    filter: &F,
+    process: &dyn process::Process,
+    syscall: &syscall::Syscall,
+) -> Result<(), errorcode::ErrorCode> {
+    match syscall {
+        syscall::Syscall::Yield { .. }
+        | syscall::Syscall::Exit { .. }
+        | syscall::Syscall::Memop { .. } => Ok(()),
+        _ => filter.filter_syscall(process, syscall).and_then(|()| {
+            syscall.driver_number().map_or(Ok(()), |driver_num| {
+                filter.filter_driver(process, driver_num)
+            })
+        }),
+    }
+}
+
+/// Check a system call against `permissions`, returning `NODEVICE` if it is
+/// routed to a driver that is not allowed.
+pub(crate) fn check_driver_permissions(
+    permissions: &DriverPermissions,
+    syscall: &syscall::Syscall,
+) -> Result<(), errorcode::ErrorCode> {
+    match syscall.driver_number() {
+        Some(driver_num) if !permissions.allows(driver_num) => Err(errorcode::ErrorCode::NODEVICE),
+        _ => Ok(()),
+    }
 }
 
 /// Implement default allow all SyscallFilter trait for unit.
 impl SyscallFilter for () {}
 
+/// A system call filter composed of an ordered list of other filters.
+///
+/// This allows a board to combine multiple independent filtering policies
+/// (for example a rate limiter and an allow list) without writing a single
+/// monolithic filter. The filters are consulted in the order they appear in
+/// the slice, and the first filter to return `Err()` determines the result;
+/// filters after it are not called. If every filter returns `Ok(())`, or the
+/// slice is empty, the system call is allowed. `record_syscall`, `on_yield`,
+/// `process_started` and `process_stopped` are forwarded to every filter in
+/// the chain, and `syscall_denied_reason` returns the reason of the first
+/// filter that has one.
+///
+/// ## Example
+///
+/// ```ignore
+/// let filter = static_init!(
+///     SyscallFilterChain,
+///     SyscallFilterChain::new(&[rate_limiter, &TbfHeaderFilterDefaultAllow {}])
+/// );
+/// ```
+pub struct SyscallFilterChain {
+    filters: &'static [&'static dyn SyscallFilter],
+}
+
+impl SyscallFilterChain {
+    pub const fn new(filters: &'static [&'static dyn SyscallFilter]) -> Self {
+        Self { filters }
+    }
+}
+
+impl SyscallFilter for SyscallFilterChain {
+    fn filter_syscall(
+        &self,
+        process: &dyn process::Process,
+        syscall: &syscall::Syscall,
+    ) -> Result<(), errorcode::ErrorCode> {
+        self.filters
+            .iter()
+            .try_for_each(|filter| filter.filter_syscall(process, syscall))
+    }
+
+    fn filter_driver(
+        &self,
+        process: &dyn process::Process,
+        driver_num: usize,
+    ) -> Result<(), errorcode::ErrorCode> {
+        self.filters
+            .iter()
+            .try_for_each(|filter| filter.filter_driver(process, driver_num))
+    }
+
+    fn record_syscall(
+        &self,
+        process: &dyn process::Process,
+        syscall: &syscall::Syscall,
+        result: &Result<(), errorcode::ErrorCode>,
+    ) {
+        // Every filter sees every system call, so that audit filters in the
+        // chain get a complete record.
+        self.filters
+            .iter()
+            .for_each(|filter| filter.record_syscall(process, syscall, result));
+    }
+
+    fn on_yield(&self, process: &dyn process::Process) {
+        self.filters
+            .iter()
+            .for_each(|filter| filter.on_yield(process));
+    }
+
+    fn process_started(&self, process: &dyn process::Process) {
+        self.filters
+            .iter()
+            .for_each(|filter| filter.process_started(process));
+    }
+
+    fn process_stopped(&self, process: &dyn process::Process) {
+        self.filters
+            .iter()
+            .for_each(|filter| filter.process_stopped(process));
+    }
+
+    fn syscall_denied_reason(&self, process: &dyn process::Process) -> Option<u32> {
+        self.filters
+            .iter()
+            .find_map(|filter| filter.syscall_denied_reason(process))
+    }
+}
+
+/// The reason `RateLimitFilter::syscall_denied_reason` gives for a system
+/// call that was rejected because the process exceeded its rate limit.
+pub const RATE_LIMIT_DENIED_REASON: u32 = 1;
+
+/// A system call filter that limits how many system calls each process may
+/// make per time window.
+///
+/// Each process may make up to `limit` filtered system calls (i.e. all but
+/// yield, exit and memop) in a window of `window_ms` milliseconds, measured
+/// from its first system call in the window. Further system calls in the same
+/// window are rejected with `BUSY`. The time source is only read, so it can be
+/// shared with other users.
+///
+/// Processes are tracked in a table of `N` entries. A process gives up its
+/// entry when it stops, or to a new process once its window has expired. If
+/// the table is full of processes in their current window, system calls of
+/// other processes are not limited, so `N` should be at least the number of
+/// processes on the board.
+///
+/// Once a process has been rate limited, `syscall_denied_reason` returns
+/// `RATE_LIMIT_DENIED_REASON` for it for as long as it keeps its entry.
+///
+/// ## Example
+///
+/// ```ignore
+/// let rate_limiter = static_init!(
+///     RateLimitFilter<'static, Rtc, NUM_PROCS>,
+///     RateLimitFilter::new(&rtc, 1000, 100)
+/// );
+/// ```
+pub struct RateLimitFilter<'a, T: Time, const N: usize> {
+    time: &'a T,
+    window: T::Ticks,
+    limit: usize,
+    table: [Cell<Option<RateLimitEntry<T::Ticks>>>; N],
+}
+
+/// System calls made by one process in its current window.
+#[derive(Clone, Copy)]
+struct RateLimitEntry<Ticks> {
+    processid: ProcessId,
+    window_start: Ticks,
+    count: usize,
+    /// Whether a system call of the process has been rate limited.
+    denied: bool,
+}
+
+impl<'a, T: Time, const N: usize> RateLimitFilter<'a, T, N> {
+    pub fn new(time: &'a T, window_ms: u32, limit: usize) -> Self {
+        Self {
+            time,
+            window: time.ticks_from_ms(window_ms),
+            limit,
+            table: [const { Cell::new(None) }; N],
+        }
+    }
+
+    fn window_expired(&self, entry: &RateLimitEntry<T::Ticks>, now: T::Ticks) -> bool {
+        now.wrapping_sub(entry.window_start) >= self.window
+    }
+
+    /// Count a system call of `processid`, see `filter_syscall`.
+    fn count_syscall(&self, processid: ProcessId) -> Result<(), errorcode::ErrorCode> {
+        let now = self.time.now();
+
+        let slot = self
+            .table
+            .iter()
+            .find(|slot| slot.get().is_some_and(|entry| entry.processid == processid))
+            .or_else(|| {
+                self.table.iter().find(|slot| {
+                    slot.get()
+                        .is_none_or(|entry| self.window_expired(&entry, now))
+                })
+            });
+        let Some(slot) = slot else {
+            // The table is full, so this process cannot be tracked.
+            return Ok(());
+        };
+
+        let entry = match slot.get() {
+            Some(entry) if entry.processid == processid && !self.window_expired(&entry, now) => {
+                entry
+            }
+            previous => RateLimitEntry {
+                processid,
+                window_start: now,
+                count: 0,
+                denied: previous.is_some_and(|entry| entry.processid == processid && entry.denied),
+            },
+        };
+        if entry.count >= self.limit {
+            slot.set(Some(RateLimitEntry {
+                denied: true,
+                ..entry
+            }));
+            return Err(errorcode::ErrorCode::BUSY);
+        }
+        slot.set(Some(RateLimitEntry {
+            count: entry.count + 1,
+            ..entry
+        }));
+        Ok(())
+    }
+
+    /// Free the entry of `processid`, if it has one.
+    fn forget(&self, processid: ProcessId) {
+        self.table
+            .iter()
+            .filter(|slot| slot.get().is_some_and(|entry| entry.processid == processid))
+            .for_each(|slot| slot.set(None));
+    }
+
+    fn denied_reason(&self, processid: ProcessId) -> Option<u32> {
+        self.table
+            .iter()
+            .filter_map(Cell::get)
+            .find(|entry| entry.processid == processid && entry.denied)
+            .map(|_| RATE_LIMIT_DENIED_REASON)
+    }
+}
+
+impl<T: Time, const N: usize> SyscallFilter for RateLimitFilter<'_, T, N> {
+    fn filter_syscall(
+        &self,
+        process: &dyn process::Process,
+        _syscall: &syscall::Syscall,
+    ) -> Result<(), errorcode::ErrorCode> {
+        self.count_syscall(process.processid())
+    }
+
+    fn process_stopped(&self, process: &dyn process::Process) {
+        self.forget(process.processid());
+    }
+
+    fn syscall_denied_reason(&self, process: &dyn process::Process) -> Option<u32> {
+        self.denied_reason(process.processid())
+    }
+}
+
 /// An allow list system call filter based on the TBF header, with a default
 /// allow all fallback.
 ///
@@ -267,6 +887,21 @@ pub trait ProcessFault {
     fn process_fault_hook(&self, process: &dyn process::Process) -> Result<(), ()> {
         Err(())
     }
+
+    /// Choose what the kernel does with `process` after it faulted and
+    /// `process_fault_hook()` did not handle the fault, e.g. to panic during
+    /// development but restart in production, or to treat some apps
+    /// differently from others.
+    ///
+    /// Returning `None` applies the process's own `ProcessFaultPolicy`, which
+    /// is what the default implementation does. This is consulted for faults
+    /// the kernel loop observes when switching to the process; faults the
+    /// process implementation detects internally, e.g. when it cannot deliver
+    /// a return value, always follow the process's policy.
+    #[allow(unused_variables)]
+    fn process_fault_policy(&self, process: &dyn process::Process) -> Option<process::FaultAction> {
+        None
+    }
 }
 
 /// Implement default ProcessFault trait for unit.
@@ -284,3 +919,111 @@ pub trait ContextSwitchCallback {
 impl ContextSwitchCallback for () {
     fn context_switch_hook(&self, _process: &dyn process::Process) {}
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::errorcode::ErrorCode;
+    use crate::hil::time::{Freq1KHz, Ticks32};
+    use crate::Kernel;
+    use std::boxed::Box;
+
+    struct MockTime {
+        now: Cell<Ticks32>,
+    }
+
+    impl MockTime {
+        fn new() -> Self {
+            Self {
+                now: Cell::new(0u32.into()),
+            }
+        }
+
+        fn advance_ms(&self, ms: u32) {
+            self.now.set(self.now.get().wrapping_add(ms.into()));
+        }
+    }
+
+    impl Time for MockTime {
+        type Frequency = Freq1KHz;
+        type Ticks = Ticks32;
+
+        fn now(&self) -> Self::Ticks {
+            self.now.get()
+        }
+    }
+
+    fn processids<const M: usize>() -> [ProcessId; M] {
+        let kernel: &'static Kernel = Box::leak(Box::new(Kernel::new(&[])));
+        core::array::from_fn(|index| ProcessId::new(kernel, index, index))
+    }
+
+    #[test]
+    fn test_rate_limit_reached() {
+        let time = MockTime::new();
+        let filter = RateLimitFilter::<_, 2>::new(&time, 100, 2);
+        let [a, b] = processids();
+
+        assert_eq!(filter.count_syscall(a), Ok(()));
+        assert_eq!(filter.count_syscall(a), Ok(()));
+        assert_eq!(filter.denied_reason(a), None);
+        assert_eq!(filter.count_syscall(a), Err(ErrorCode::BUSY));
+        assert_eq!(filter.denied_reason(a), Some(RATE_LIMIT_DENIED_REASON));
+
+        // Other processes are limited separately.
+        assert_eq!(filter.count_syscall(b), Ok(()));
+        assert_eq!(filter.denied_reason(b), None);
+    }
+
+    #[test]
+    fn test_rate_limit_window_expiry() {
+        let time = MockTime::new();
+        let filter = RateLimitFilter::<_, 1>::new(&time, 100, 1);
+        let [a] = processids();
+
+        assert_eq!(filter.count_syscall(a), Ok(()));
+        time.advance_ms(99);
+        assert_eq!(filter.count_syscall(a), Err(ErrorCode::BUSY));
+        time.advance_ms(1);
+        assert_eq!(filter.count_syscall(a), Ok(()));
+
+        // The denial is still reported in the new window.
+        assert_eq!(filter.denied_reason(a), Some(RATE_LIMIT_DENIED_REASON));
+    }
+
+    #[test]
+    fn test_rate_limit_table_full() {
+        let time = MockTime::new();
+        let filter = RateLimitFilter::<_, 1>::new(&time, 100, 1);
+        let [a, b] = processids();
+
+        assert_eq!(filter.count_syscall(a), Ok(()));
+        assert_eq!(filter.count_syscall(b), Ok(()));
+        assert_eq!(filter.count_syscall(b), Ok(()));
+        assert_eq!(filter.denied_reason(b), None);
+
+        // Once the window of `a` has expired, `b` takes over its entry.
+        time.advance_ms(100);
+        assert_eq!(filter.count_syscall(b), Ok(()));
+        assert_eq!(filter.count_syscall(b), Err(ErrorCode::BUSY));
+        assert_eq!(filter.denied_reason(a), None);
+    }
+
+    #[test]
+    fn test_rate_limit_process_stopped() {
+        let time = MockTime::new();
+        let filter = RateLimitFilter::<_, 1>::new(&time, 100, 1);
+        let [a, b] = processids();
+
+        assert_eq!(filter.count_syscall(a), Ok(()));
+        assert_eq!(filter.count_syscall(a), Err(ErrorCode::BUSY));
+        filter.forget(a);
+        assert_eq!(filter.denied_reason(a), None);
+
+        // The freed entry is available to other processes right away.
+        assert_eq!(filter.count_syscall(b), Ok(()));
+        assert_eq!(filter.count_syscall(b), Err(ErrorCode::BUSY));
+    }
+}
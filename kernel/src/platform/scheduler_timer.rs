@@ -9,6 +9,7 @@
 
 use crate::hil::time::{self, Frequency, Ticks};
 
+use core::cmp;
 use core::num::NonZeroU32;
 
 /// Interface for the system scheduler timer.
@@ -125,6 +126,98 @@ pub trait SchedulerTimer {
     /// `start()`, the return value is unspecified and implementations may
     /// return whatever they like.
     fn get_remaining_us(&self) -> Option<NonZeroU32>;
+
+    /// Return the number of microseconds remaining in the current timeslice,
+    /// or `None` if no timeslice is running (it was never started, has been
+    /// reset, or has expired).
+    ///
+    /// Unlike `get_remaining_us()`, this may be called at any time and does not
+    /// affect the kernel's own expiration checks. This lets code running on
+    /// behalf of a process, such as a capsule handling a command, decide
+    /// whether to start a long operation now or let the process yield first.
+    /// The default implementation returns `None`, for timers that cannot
+    /// provide this without side effects.
+    fn remaining_us(&self) -> Option<u32> {
+        None
+    }
+
+    /// Return the longest timeslice, in microseconds, that `start()` can
+    /// accurately time.
+    ///
+    /// Schedulers that let the board pick the timeslice length can use this to
+    /// reject lengths the timer cannot represent. The default implementation
+    /// places no limit.
+    fn max_timeslice_us(&self) -> u32 {
+        u32::MAX
+    }
+
+    /// Stop the running timeslice from counting down until `resume()` is
+    /// called, e.g. while the kernel does long running work on its own
+    /// behalf that should not be charged to the process. The timer does not
+    /// expire while paused, and `get_remaining_us()` and `remaining_us()`
+    /// report the time that was left when it was paused.
+    ///
+    /// Calls do not nest: a second `pause()` has no effect, and one `resume()`
+    /// undoes any number of them. `start()` and `reset()` end a pause, so a
+    /// later `resume()` is a no-op and the paused time is not added to the
+    /// next timeslice. `arm()` and `disarm()` may be called while paused and
+    /// take effect once resumed. The default implementation does nothing,
+    /// i.e. the timeslice keeps counting.
+    fn pause(&self) {}
+
+    /// Continue counting down a timeslice paused with `pause()`. Does nothing
+    /// if the timer is not paused.
+    fn resume(&self) {}
+
+    /// Select which time is charged to a process's timeslice, see
+    /// `Accounting`. The policy stays in effect for all later timeslices.
+    ///
+    /// Excluding interrupts relies on `pause()` and `resume()`, so timers that
+    /// do not implement them ignore this, which is what the default
+    /// implementation does. `accounting()` returns the policy in effect.
+    fn set_accounting(&self, _policy: Accounting) {}
+
+    /// Return the policy set with `set_accounting()`. The default
+    /// implementation returns `Accounting::ChargeAll`.
+    fn accounting(&self) -> Accounting {
+        Accounting::ChargeAll
+    }
+
+    /// Busy-wait for `us` microseconds, timed by the timer's hardware
+    /// counter, e.g. for short delays a driver needs during initialization
+    /// before interrupts are set up. Unlike a loop of NOPs, this stays correct
+    /// when the clock configuration changes.
+    ///
+    /// This blocks the whole kernel and uses the timer itself, ending any
+    /// running timeslice and leaving the timer reset. It must therefore only be
+    /// used during board bring-up, not once the kernel loop is running.
+    ///
+    /// The default implementation waits for `start()`ed timeslices of at most
+    /// `max_timeslice_us()` to expire, polling `get_remaining_us()`.
+    fn delay_us(&self, us: u32) {
+        let mut remaining = us;
+        while let Some(chunk) = NonZeroU32::new(cmp::min(remaining, self.max_timeslice_us())) {
+            self.reset();
+            self.start(chunk);
+            while self.get_remaining_us().is_some() {}
+            remaining -= chunk.get();
+        }
+        self.reset();
+    }
+}
+
+/// Which time a `SchedulerTimer` charges to the running process's timeslice.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Accounting {
+    /// Charge all wall-clock time from the start of the timeslice until it
+    /// ends, including time the kernel spends on interrupts that arrived
+    /// while the process was running. This is the default.
+    ChargeAll,
+    /// Pause the timeslice from the moment an interrupt stops the process
+    /// until the process runs again, so the kernel's handling of the
+    /// interrupt is not charged to it. Only the interrupt's top half, which
+    /// runs before the kernel regains control, is still charged.
+    ExcludeInterrupts,
 }
 
 /// A dummy `SchedulerTimer` implementation in which the timer never expires.
@@ -143,6 +236,10 @@ impl SchedulerTimer for () {
     fn get_remaining_us(&self) -> Option<NonZeroU32> {
         NonZeroU32::new(10000) // choose arbitrary large value
     }
+
+    fn delay_us(&self, _us: u32) {
+        // This timer never expires, so it cannot time a delay.
+    }
 }
 
 /// Implementation of SchedulerTimer trait on top of a virtual alarm.
@@ -220,4 +317,19 @@ impl<A: 'static + time::Alarm<'static>> SchedulerTimer for VirtualSchedulerTimer
             NonZeroU32::new(((diff * 1_000_000) / hertz) as u32)
         }
     }
+
+    fn max_timeslice_us(&self) -> u32 {
+        // `get_remaining_us()` treats an alarm one second or more away as
+        // expired.
+        999_999
+    }
+
+    fn remaining_us(&self) -> Option<u32> {
+        // Reading the alarm has no side effects.
+        if self.alarm.is_armed() {
+            self.get_remaining_us().map(NonZeroU32::get)
+        } else {
+            None
+        }
+    }
 }
@@ -34,6 +34,56 @@ pub trait WatchDog {
     fn resume(&self) {
         self.tickle();
     }
+
+    /// Returns the time, in milliseconds, since the watchdog was last tickled,
+    /// or `None` if the implementation does not track this (or has not been
+    /// tickled yet).
+    ///
+    /// Comparing this against the watchdog period shows how close the kernel
+    /// came to a watchdog reset, which helps diagnose sporadic resets.
+    fn time_since_last_pet(&self) -> Option<u32> {
+        None
+    }
+
+    /// Ask the watchdog to call `client` some time before it resets the chip,
+    /// so that the kernel can capture diagnostics of the hang, e.g. by
+    /// panicking so that the panic handler prints the chip state.
+    ///
+    /// How long before the reset the warning is given is implementation
+    /// specific. Implementations without a warning stage ignore the client,
+    /// which is what the default implementation does.
+    fn on_warning(&self, _client: &'static dyn WatchDogClient) {}
+
+    /// Run `f`, a legitimately long operation such as erasing all of flash,
+    /// without the watchdog resetting the chip because the kernel loop did
+    /// not tickle it in time. The normal timeout applies again, starting
+    /// afresh, once `f` returns.
+    ///
+    /// Implementations lengthen the timeout while `f` runs where the hardware
+    /// allows it, so that a hang inside `f` is still caught eventually. The
+    /// default implementation suspends the watchdog instead, so a hang inside
+    /// `f` is never caught. Either way the watchdog protects less while `f`
+    /// runs, so only wrap work that is known to take long and to terminate.
+    fn with_extended_timeout<R>(&self, f: impl FnOnce() -> R) -> R
+    where
+        Self: Sized,
+    {
+        self.suspend();
+        let result = f();
+        self.resume();
+        result
+    }
+}
+
+/// Client of a watchdog with a warning stage, see `WatchDog::on_warning()`.
+pub trait WatchDogClient {
+    /// Called when the watchdog is about to reset the chip.
+    ///
+    /// This is called directly from the watchdog's interrupt handler rather
+    /// than from the kernel loop, as the kernel may be the one that is hung.
+    /// It must therefore not rely on the state of the kernel being
+    /// consistent.
+    fn watchdog_warning(&self);
 }
 
 /// Implement default WatchDog trait for unit.
@@ -455,6 +455,10 @@ pub trait Process {
     /// take in regards to the faulted process.
     fn set_fault_state(&self);
 
+    /// Put this process in the fault state like `set_fault_state()`, but take
+    /// `action` instead of the action chosen by the process's fault policy.
+    fn set_fault_state_with_action(&self, action: FaultAction);
+
     /// Start a terminated process. This function can only be called on a
     /// terminated process.
     ///
@@ -842,6 +846,11 @@ pub trait Process {
     /// various process data structures.
     fn get_sizes(&self) -> ProcessSizes;
 
+    /// Fill `out` with the memory ranges this process can access according
+    /// to its MPU configuration, such as its RAM and flash, and return how
+    /// many were written. See `MPU::config_regions()`.
+    fn mpu_regions(&self, out: &mut [mpu::MpuRegionInfo]) -> usize;
+
     /// Write stored state as a binary blob into the `out` slice. Returns the
     /// number of bytes written to `out` on success.
     ///
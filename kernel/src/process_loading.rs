@@ -48,6 +48,11 @@ pub enum ProcessLoadError {
     /// (current) MPU constraints and process requirements.
     MpuConfigurationError,
 
+    /// The MPU provides fewer regions than the kernel needs to isolate a
+    /// process. This is a mismatch between the board's MPU configuration and
+    /// the kernel, and no process can be loaded until it is fixed.
+    MpuInsufficientRegions { required: usize, available: usize },
+
     /// A process specified a fixed memory address that it needs its memory
     /// range to start at, and the kernel did not or could not give the process
     /// a memory region starting at that address.
@@ -85,6 +90,15 @@ impl fmt::Debug for ProcessLoadError {
                 write!(f, "Configuring the MPU failed")
             }
 
+            ProcessLoadError::MpuInsufficientRegions {
+                required,
+                available,
+            } => write!(
+                f,
+                "MPU has too few regions for an app, Required:{}, Available:{}",
+                required, available
+            ),
+
             ProcessLoadError::MemoryAddressMismatch {
                 actual_address,
                 expected_address,
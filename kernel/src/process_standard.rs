@@ -631,7 +631,10 @@ impl<C: Chip, D: 'static + ProcessStandardDebug> Process for ProcessStandard<'_,
     fn set_fault_state(&self) {
         // Use the per-process fault policy to determine what action the kernel
         // should take since the process faulted.
-        let action = self.fault_policy.action(self);
+        self.set_fault_state_with_action(self.fault_policy.action(self));
+    }
+
+    fn set_fault_state_with_action(&self, action: FaultAction) {
         match action {
             FaultAction::Panic => {
                 // process faulted. Panic and print status
@@ -1426,6 +1429,11 @@ impl<C: Chip, D: 'static + ProcessStandardDebug> Process for ProcessStandard<'_,
         }
     }
 
+    fn mpu_regions(&self, out: &mut [mpu::MpuRegionInfo]) -> usize {
+        self.mpu_config
+            .map_or(0, |config| self.chip.mpu().config_regions(config, out))
+    }
+
     fn print_full_process(&self, writer: &mut dyn Write) {
         if !config::CONFIG.debug_panics {
             return;
@@ -1536,6 +1544,10 @@ impl<C: 'static + Chip, D: 'static + ProcessStandardDebug> ProcessStandard<'_, C
     // Memory offset to make room for this process's metadata.
     const PROCESS_STRUCT_OFFSET: usize = mem::size_of::<ProcessStandard<C, D>>();
 
+    // Number of MPU regions needed to isolate a process: one for its flash and
+    // at least one for its RAM.
+    const MIN_MPU_REGIONS: usize = 2;
+
     /// Create a `ProcessStandard` object based on the found `ProcessBinary`.
     pub(crate) unsafe fn create<'a>(
         kernel: &'static Kernel,
@@ -1551,6 +1563,30 @@ impl<C: 'static + Chip, D: 'static + ProcessStandardDebug> ProcessStandard<'_, C
         let process_name = pb.header.get_package_name();
         let process_ram_requested_size = pb.header.get_minimum_app_ram_size() as usize;
 
+        // Make sure the MPU can describe the process's memory layout at all.
+        // An MPU reporting no regions does not provide any protection (e.g.
+        // `()`), so there is nothing to check.
+        let available_regions = chip.mpu().number_total_regions();
+        if available_regions != 0 && available_regions < Self::MIN_MPU_REGIONS {
+            if config::CONFIG.debug_load_processes {
+                debug!(
+                    "[!] flash={:#010X}-{:#010X} process={:?} - MPU has {} regions, need {}",
+                    pb.flash.as_ptr() as usize,
+                    pb.flash.as_ptr() as usize + pb.flash.len() - 1,
+                    process_name,
+                    available_regions,
+                    Self::MIN_MPU_REGIONS
+                );
+            }
+            return Err((
+                ProcessLoadError::MpuInsufficientRegions {
+                    required: Self::MIN_MPU_REGIONS,
+                    available: available_regions,
+                },
+                remaining_memory,
+            ));
+        }
+
         // Initialize MPU region configuration.
         let mut mpu_config = match chip.mpu().new_config() {
             Some(mpu_config) => mpu_config,
@@ -1571,10 +1607,11 @@ impl<C: 'static + Chip, D: 'static + ProcessStandardDebug> ProcessStandard<'_, C
         {
             if config::CONFIG.debug_load_processes {
                 debug!(
-                        "[!] flash={:#010X}-{:#010X} process={:?} - couldn't allocate MPU region for flash",
+                        "[!] flash={:#010X}-{:#010X} process={:?} - couldn't allocate MPU region for flash (min region size {:#X})",
                         pb.flash.as_ptr() as usize,
                         pb.flash.as_ptr() as usize + pb.flash.len() - 1,
-                        process_name
+                        process_name,
+                        chip.mpu().min_region_size()
                     );
             }
             return Err((ProcessLoadError::MpuInvalidFlashLength, remaining_memory));
@@ -23,9 +23,11 @@ use core::num::NonZeroU32;
 
 use crate::collections::list::{List, ListLink, ListNode};
 use crate::platform::chip::Chip;
+use crate::platform::scheduler_timer::SchedulerTimer;
 use crate::process::Process;
 use crate::process::StoppedExecutingReason;
 use crate::scheduler::{Scheduler, SchedulingDecision};
+use crate::ErrorCode;
 
 /// A node in the linked list the scheduler uses to track processes
 /// Each node holds a pointer to a slot in the processes array
@@ -52,7 +54,7 @@ impl<'a> ListNode<'a, RoundRobinProcessNode<'a>> for RoundRobinProcessNode<'a> {
 /// Round Robin Scheduler
 pub struct RoundRobinSched<'a> {
     time_remaining: Cell<u32>,
-    timeslice_length: u32,
+    timeslice_length: Cell<u32>,
     pub processes: List<'a, RoundRobinProcessNode<'a>>,
     last_rescheduled: Cell<bool>,
 }
@@ -67,11 +69,34 @@ impl<'a> RoundRobinSched<'a> {
     pub const fn new_with_time(time_us: u32) -> RoundRobinSched<'a> {
         RoundRobinSched {
             time_remaining: Cell::new(time_us),
-            timeslice_length: time_us,
+            timeslice_length: Cell::new(time_us),
             processes: List::new(),
             last_rescheduled: Cell::new(false),
         }
     }
+
+    /// Change the length of the timeslices given to processes, e.g. to trade
+    /// responsiveness for less context switching overhead. `timer` is the
+    /// scheduler timer the kernel uses with this scheduler, which the length
+    /// is checked against. The new length applies from the next fresh
+    /// timeslice on.
+    ///
+    /// Returns `INVAL` if `time_us` is zero, and `SIZE` if it is longer than
+    /// `timer` can time.
+    pub fn set_timeslice_us<T: SchedulerTimer>(
+        &self,
+        time_us: u32,
+        timer: &T,
+    ) -> Result<(), ErrorCode> {
+        if time_us == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        if time_us > timer.max_timeslice_us() {
+            return Err(ErrorCode::SIZE);
+        }
+        self.timeslice_length.set(time_us);
+        Ok(())
+    }
 }
 
 impl<C: Chip> Scheduler<C> for RoundRobinSched<'_> {
@@ -118,8 +143,8 @@ impl<C: Chip> Scheduler<C> for RoundRobinSched<'_> {
             self.time_remaining.get()
         } else {
             // grant a fresh timeslice
-            self.time_remaining.set(self.timeslice_length);
-            self.timeslice_length
+            self.time_remaining.set(self.timeslice_length.get());
+            self.timeslice_length.get()
         };
         // Why should this panic?
         let non_zero_timeslice = NonZeroU32::new(timeslice).unwrap();
@@ -331,6 +331,35 @@ impl Syscall {
         }
     }
 
+    /// Replace the `driver_number` of the syscall classes that use driver
+    /// numbers with the result of `f`. Other syscalls are returned unchanged.
+    pub(crate) fn map_driver_number(mut self, f: impl FnOnce(usize) -> usize) -> Self {
+        match self {
+            Syscall::Subscribe {
+                ref mut driver_number,
+                ..
+            }
+            | Syscall::Command {
+                ref mut driver_number,
+                ..
+            }
+            | Syscall::ReadWriteAllow {
+                ref mut driver_number,
+                ..
+            }
+            | Syscall::UserspaceReadableAllow {
+                ref mut driver_number,
+                ..
+            }
+            | Syscall::ReadOnlyAllow {
+                ref mut driver_number,
+                ..
+            } => *driver_number = f(*driver_number),
+            _ => {}
+        }
+        self
+    }
+
     /// Get the `subdriver_number` for the syscall classes that use sub driver
     /// numbers.
     pub fn subdriver_number(&self) -> Option<usize> {
@@ -509,6 +538,22 @@ impl SyscallReturn {
             SyscallReturn::YieldWaitFor(_, _, _) => true,
         }
     }
+
+    /// Returns the [`ErrorCode`] of a failure [`SyscallReturn`] as `Err()`,
+    /// or `Ok(())` if it is any success type.
+    pub(crate) fn result(&self) -> Result<(), ErrorCode> {
+        match *self {
+            SyscallReturn::Failure(err)
+            | SyscallReturn::FailureU32(err, _)
+            | SyscallReturn::FailureU32U32(err, _, _)
+            | SyscallReturn::FailureU64(err, _)
+            | SyscallReturn::AllowReadWriteFailure(err, _, _)
+            | SyscallReturn::UserspaceReadableAllowFailure(err, _, _)
+            | SyscallReturn::AllowReadOnlyFailure(err, _, _)
+            | SyscallReturn::SubscribeFailure(err, _, _) => Err(err),
+            _ => Ok(()),
+        }
+    }
 }
 
 // ---------- USERSPACE KERNEL BOUNDARY ----------